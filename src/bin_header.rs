@@ -1,16 +1,97 @@
 pub const HEADER_SIZE: usize = 16;
 
+// BIN files are padded with 0xFF to a multiple of this
+pub const FILE_ALIGNMENT: usize = 16;
+
+// tw and th are believed to line up with the PS2 GS TEX0 register's TW/TH fields,
+// the sprite being uploaded to a texture page of 2^tw by 2^th pixels (the GS caps
+// both at 10). 0 in both is what tools that don't know the page size write (ghoul included).
+pub const TEXTURE_EXPONENT_MAX: u16 = 10;
+
+// 0x20 is a palette of 4-byte RGBA colors and 0 means there's no palette. Other
+// clut values show up in game data, but what they mean hasn't been worked out.
+pub const CLUT_NONE: u16 = 0x00;
+pub const CLUT_32: u16 = 0x20;
+
 pub struct BinHeader {
 	pub compressed: bool,
 	pub clut: u16,
 	pub bit_depth: u16,
 	pub width: u16,
 	pub height: u16,
-	pub tw: u16,
-	pub th: u16,
+	pub tw: TextureExponent,
+	pub th: TextureExponent,
 	pub hash: u16,
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub struct TextureExponent(pub u16);
+
+
+// Smallest texture page side that holds size pixels
+pub fn fit_exponent(size: u16) -> TextureExponent {
+	let mut exponent: u16 = 0;
+	while (1u32 << exponent) < size as u32 {
+		exponent += 1;
+	}
+	
+	return TextureExponent(exponent);
+}
+
+
+pub fn exponent_valid(exponent: TextureExponent) -> bool {
+	return exponent.0 <= TEXTURE_EXPONENT_MAX;
+}
+
+
+// Unset tw/th (both 0) always count as matching, otherwise the page has to hold the
+// sprite. A page bigger than needed still matches.
+pub fn texture_matches(header: &BinHeader) -> bool {
+	if header.tw.0 == 0 && header.th.0 == 0 {
+		return true;
+	}
+	
+	if !exponent_valid(header.tw) || !exponent_valid(header.th) {
+		return false;
+	}
+	
+	return header.tw.0 >= fit_exponent(header.width).0 && header.th.0 >= fit_exponent(header.height).0;
+}
+
+
+// Packed size of the pixel data, in bytes. Rows aren't aligned on their own, same as
+// the compressed stream, so an odd-width 4 bpp row shares its last byte with the next row.
+pub fn pixel_byte_count(header: &BinHeader) -> usize {
+	let pixel_count: usize = header.width as usize * header.height as usize;
+	return (pixel_count * header.bit_depth as usize + 7) / 8;
+}
+
+
+// Bytes per palette color, None for clut values that aren't known
+pub fn clut_entry_size(clut: u16) -> Option<usize> {
+	match clut {
+		CLUT_NONE => return Some(0),
+		CLUT_32 => return Some(4),
+		_ => return None,
+	}
+}
+
+
+// Size of the embedded palette in bytes, unknown clut values count as no palette
+pub fn palette_length(header: &BinHeader) -> usize {
+	let color_count: usize = 2usize.pow(header.bit_depth as u32);
+	return clut_entry_size(header.clut).unwrap_or(0) * color_count;
+}
+
+
+// Embedded palette as RGBA, tables of unknown clut values can't be read
+pub fn get_palette(data: &[u8], clut: u16) -> Vec<u8> {
+	match clut {
+		CLUT_32 => return data.to_vec(),
+		_ => return Vec::new(),
+	}
+}
+
 
 pub fn get_header(data: Vec<u8>) -> BinHeader {	
 	return BinHeader {
@@ -32,13 +113,13 @@ pub fn get_header(data: Vec<u8>) -> BinHeader {
 			data[0x08], data[0x09]
 		]),
 		
-		tw: u16::from_le_bytes([
+		tw: TextureExponent(u16::from_le_bytes([
 			data[0x0A], data[0x0B]
-		]),
+		])),
 		
-		th: u16::from_le_bytes([
+		th: TextureExponent(u16::from_le_bytes([
 			data[0x0C], data[0x0D]
-		]),
+		])),
 		
 		hash: u16::from_le_bytes([
 			data[0x0E], data[0x0F]
@@ -66,14 +147,61 @@ pub fn get_bytes(header: BinHeader) -> Vec<u8> {
 	// height
 	return_vector.extend_from_slice(&header.height.to_le_bytes());
 	
-	// tw (unknown)
-	return_vector.extend_from_slice(&header.tw.to_le_bytes());
+	// tw (texture page width exponent)
+	return_vector.extend_from_slice(&header.tw.0.to_le_bytes());
 	
-	// th (unknown)
-	return_vector.extend_from_slice(&header.th.to_le_bytes());
+	// th (texture page height exponent)
+	return_vector.extend_from_slice(&header.th.0.to_le_bytes());
 	
-	// hash (generation method unknown, doesn't affect result)
+	// hash (see generate_hash(), doesn't affect result)
 	return_vector.extend_from_slice(&header.hash.to_le_bytes());
 	
 	return return_vector;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn header(width: u16, height: u16, bit_depth: u16) -> BinHeader {
+		return BinHeader {
+			compressed: false,
+			clut: CLUT_NONE,
+			bit_depth: bit_depth,
+			width: width,
+			height: height,
+			tw: TextureExponent(0),
+			th: TextureExponent(0),
+			hash: 0,
+		};
+	}
+	
+	#[test]
+	fn pixel_byte_count_per_depth() {
+		assert_eq!(pixel_byte_count(&header(16, 4, 8)), 64);
+		assert_eq!(pixel_byte_count(&header(16, 4, 4)), 32);
+		assert_eq!(pixel_byte_count(&header(16, 4, 2)), 16);
+		assert_eq!(pixel_byte_count(&header(16, 4, 1)), 8);
+	}
+	
+	#[test]
+	fn pixel_byte_count_packs_across_rows() {
+		// 3x3 at 4 bpp is 9 nibbles, not 3 rows of 2 bytes
+		assert_eq!(pixel_byte_count(&header(3, 3, 4)), 5);
+		assert_eq!(pixel_byte_count(&header(3, 2, 4)), 3);
+		assert_eq!(pixel_byte_count(&header(5, 1, 1)), 1);
+		assert_eq!(pixel_byte_count(&header(5, 3, 2)), 4);
+	}
+	
+	#[test]
+	fn texture_matches_larger_pages() {
+		let mut sprite: BinHeader = header(100, 30, 4);
+		
+		// Exact fit is 7 by 5
+		for (tw, th, matches) in [(7, 5, true), (8, 5, true), (10, 10, true), (6, 5, false), (7, 4, false), (11, 5, false), (0, 0, true)] {
+			sprite.tw = TextureExponent(tw);
+			sprite.th = TextureExponent(th);
+			assert_eq!(texture_matches(&sprite), matches, "tw {} th {}", tw, th);
+		}
+	}
+}
@@ -13,12 +13,26 @@ pub mod sprite_get;
 pub mod sprite_make;
 pub mod sprite_compress;
 pub mod sprite_transform;
+pub mod remap_table;
+pub mod palette_report;
+pub mod palette_swatch;
+pub mod sprite_info;
+pub mod sprite_animate;
+pub mod sprite_archive;
+pub mod palette_set;
+
+#[cfg(test)]
+mod test_file;
 
 use crate::shared_types::{
 	Parameters,
 	SpriteData,
 	SpriteFormat,
 	HashMode,
+	ReportMode,
+	SwatchMode,
+	ArchiveMode,
+	PaletteSetMode,
 };
 
 
@@ -46,13 +60,64 @@ pub fn main() {
 	
 	let parameters: Parameters = opt_parameters.unwrap();
 	
-	if parameters.forced_bit_depth {
+	if parameters.forced_bit_depth || parameters.compact_4bpp {
 		println!("Note: Changing a sprite's color depth could produce incorrect results ingame.");
 	}
 	
+	// Reports only read sprites
+	if parameters.report_mode != ReportMode::NONE {
+		palette_report::report(parameters);
+		return;
+	}
+	
+	if parameters.info_mode {
+		sprite_info::info(parameters);
+		return;
+	}
+	
 	println!("Working...");
 	let instant = Instant::now();
 	
+	if parameters.animate_format != SpriteFormat::NONE {
+		let frame_count: usize = sprite_animate::animate(parameters);
+		println!("Animated {} frames in {}ms.", frame_count, instant.elapsed().as_millis());
+		return;
+	}
+	
+	if parameters.archive_mode != ArchiveMode::NONE {
+		let archive_mode: ArchiveMode = parameters.archive_mode;
+		let sprite_count: usize = sprite_archive::archive(parameters);
+		
+		match archive_mode {
+			ArchiveMode::LIST => print!("Listed"),
+			ArchiveMode::EXTRACT => print!("Extracted"),
+			ArchiveMode::REPACK => print!("Replaced"),
+			ArchiveMode::NONE => (),
+		}
+		
+		println!(" {} sprites in {}ms.", sprite_count, instant.elapsed().as_millis());
+		return;
+	}
+	
+	if parameters.palette_set_mode != PaletteSetMode::NONE {
+		let palette_set_mode: PaletteSetMode = parameters.palette_set_mode;
+		let palette_count: usize = palette_set::palette_set(parameters);
+		
+		match palette_set_mode {
+			PaletteSetMode::REBUILD => print!("Replaced"),
+			_ => print!("Exported"),
+		}
+		
+		println!(" {} palettes in {}ms.", palette_count, instant.elapsed().as_millis());
+		return;
+	}
+	
+	if parameters.swatch_mode != SwatchMode::NONE {
+		let swatch_count: usize = palette_swatch::swatch(parameters);
+		println!("Made {} swatches in {}ms.", swatch_count, instant.elapsed().as_millis());
+		return;
+	}
+	
 	if parameters.directory_mode {
 		process_directory(parameters);
 	}
@@ -69,10 +134,10 @@ pub fn main() {
 pub fn help_message() {
 	println!();
 	println!("Tool for handling GGXX AC+R sprites.");
-	println!("Can convert and reindex PNG-, RAW-, BIN-, and BMP-format sprites.");
+	println!("Can convert and reindex PNG-, RAW-, BIN-, BMP-, GIF-, TGA-, and PCX-format sprites.");
 	println!();
 	println!("Usage:");
-	println!("    ghoul -i <input path> [-f format] [-o <output path>] [-w] [-l] [-p/-c] [-q] [-rgb] [-4/-8] [-r] [-hs/-hi] [-u]");
+	println!("    ghoul -i <input path> [-f format] [-o <output path>] [-w] [-l] [-info] [-al/-ax/-ar <dir>] [-pse <format>/-psr <dir>] [-ps16] [-rep <format>] [-a <format>] [-d <ms>] [-sw <style>] [-p/-c] [-q] [-cs] [-pa] [-rgb] [-4/-4c/-8] [-r/-rp] [-m <file>] [-mp] [-hs/-hi/-hg] [-tw <n>] [-th <n>] [-tf] [-u] [-rle] [-bh <header>]");
 	println!();
 	println!("To process full directories, use an asterisk as the input file name (e.g. '-i path/*.png').");
 	println!();
@@ -81,25 +146,61 @@ pub fn help_message() {
 	println!("  Files:");
 	println!("    -i   or -input   <input path>  Set the input file or directory");
 	println!("    -o   or -output  <path>        Set output path, defaults to the current directory if not specified");
-	println!("    -f   or -format  <format>      Convert sprites (formats: 'png', 'raw', 'bin', 'bmp')");
+	println!("    -f   or -format  <format>      Convert sprites (formats: 'png', 'raw', 'bin', 'bmp', 'gif', 'tga', 'pcx')");
 	println!("    -w   or -overwrite             Overwrite pre-existing files");
 	println!("    -l   or -list                  Print each filename to the console as it's processed");
+	println!("    -info                          Print sprite headers instead of converting, warning about mismatched tw/th");
+	println!("    -rep or -report  <format>      Report palette index usage instead of converting ('text', 'csv', 'json')");
+	println!("    -a   or -animate <format>      Join a directory of frames into one animation (formats: 'gif', 'apng')");
+	println!("    -d   or -delay   <ms>          Set the animation frame delay in milliseconds, defaults to 100");
+	println!("    -sw  or -swatch  <style>       Render input palettes (.act, or a sprite's palette) as PNG swatches ('grid', 'labels')");
+	println!("");
+	println!("  Archives (any file with BIN sprites packed inside, one at a time):");
+	println!("    -al  or -archive-list          List the sprites found in the archive");
+	println!("    -ax  or -archive-extract       Extract every sprite as '<archive>_000.bin', '<archive>_001.bin'...");
+	println!("    -ar  or -archive-repack <dir>  Put the BINs in this directory back into their slots, writing a new archive");
+	println!("                                   Larger BINs move the data after them, the archive's offsets aren't updated");
+	println!("");
+	println!("  Palette sets (a character's palette slots stored together, one at a time):");
+	println!("    -pse or -palset-export <fmt>   Export every palette as '<set>_00.act'... (formats: 'act', 'gpl')");
+	println!("    -psr or -palset-rebuild <dir>  Put the edited ACT/GPL palettes in this directory back into the set");
+	println!("    -ps16 or -palset-16            Read the set as 16-color palettes even if its size fits 256-color ones");
 	println!("");
 	println!("  Palette (no effect on RAW files):");
 	println!("    -p   or -palette <pal file>    Color output sprite using this .act palette");
+	println!("                                   A directory or comma-separated list of palettes writes one output per palette");
 	println!("    -c   or -palcopy               Copy source sprite's palette to output sprite (overrides -palette)");
 	println!("    -q   or -opaque                Make output sprite's palette completely opaque");
+	println!("    -cs  or -clut-swizzle          Swap palette entries 8-15 and 16-23 in every block of 32 (PS2 CLUT order <-> linear)");
+	println!("    -pa  or -ps2-alpha             Scale palette alpha between BIN (0x80 opaque) and other formats (0xFF opaque)");
 	println!("");
 	println!("  Image processing:");
 	println!("    -rgb or -as-rgb                Force inputs to be treated as RGB (except RAWs and grayscale)");
+	println!("    -1   or -force-1bpp            Force output to 1-bit color depth (except RAWs)");
+	println!("    -2   or -force-2bpp            Force output to 2-bit color depth (except RAWs)");
 	println!("    -4   or -force-4bpp            Force output to 4-bit color depth (except RAWs)");
+	println!("    -4c  or -compact-4bpp          Convert output to 4-bit color depth, compacting used colors into 16 slots");
 	println!("    -8   or -force-8bpp            Force output to 8-bit color depth (except RAWs)");
 	println!("    -r   or -reindex               Reindex output");
+	println!("    -rp  or -reindex-pal           Reindex output and its palette, keeping colors intact");
+	println!("    -m   or -remap   <remap file>  Remap output indices using a table of 256 entries or 'from:to' lines");
+	println!("    -mp  or -remap-pal             Also move palette colors along with remapped indices");
 	println!("");
 	println!("  BIN sprites:");
 	println!("    -hs  or -hash-set <number>     Output sprites with set hash <number> (0 to 65535)");
 	println!("    -hi  or -hash-inc <number>     Output sprites with incremental hashes starting at <number>");
+	println!("    -hg  or -hash-gen              Generate new hashes even if the source sprite has one");
+	println!("    -tw  <number>                  Output sprites with texture page width 2^<number> (0 to 10), instead of the source's");
+	println!("    -th  <number>                  Output sprites with texture page height 2^<number> (0 to 10), instead of the source's");
+	println!("    -tf  or -tex-fit               Output sprites with the smallest tw/th that fit their dimensions");
 	println!("    -u   or -uncompressed          Output uncompressed sprites");
+	println!("");
+	println!("  TGA and BMP sprites:");
+	println!("    -rle                           Output run-length encoded sprites (BMPs use at least an 'info' header)");
+	println!("");
+	println!("  BMP sprites:");
+	println!("    -bh  or -bmp-header <header>   Set the BMP header ('core' (default), 'info', 'v4', 'v5')");
+	println!("                                   'v4' and 'v5' keep palette alpha");
 	println!();
 }
 
@@ -107,23 +208,44 @@ pub fn help_message() {
 fn process_file(parameters: Parameters) {
 	if parameters.verbose {
 		match parameters.source_path.file_name() {
-			Some(name) => println!("Processing '{}'", name.to_str().unwrap()),
+			Some(name) => println!("Processing '{}'", name.to_string_lossy()),
 			_ => (),
 		}
 	}
 	
-	let mut data: SpriteData = SpriteData::default();
+	let data: SpriteData = load_sprite(&parameters);
 	
-	match parameters.source_format {
-		SpriteFormat::PNG => data = sprite_get::get_png(&parameters.source_path),
-		SpriteFormat::RAW => data = sprite_get::get_raw(&parameters.source_path),
-		SpriteFormat::BIN => data = sprite_get::get_bin(&parameters.source_path),
-		SpriteFormat::BMP => data = sprite_get::get_bmp(&parameters.source_path),
-		_ => println!("main::process_file() error: Invalid source format provided"),
+	if data.width == 0 || data.height == 0 {
+		return;
 	}
 	
+	// -palette with multiple palettes, one output per palette
+	if !parameters.palette_batch.is_empty() {
+		let palette_batch: Vec<PathBuf> = parameters.palette_batch.clone();
+		
+		for palette in 0..palette_batch.len() {
+			let mut batch_parameters: Parameters = parameters.clone();
+			batch_parameters.palette_file = palette_batch[palette].clone();
+			batch_parameters.target_suffix = format!("_pal{:02}", palette);
+			
+			let batch_data: SpriteData = apply_palette(&batch_parameters, data.clone());
+			write_sprite(batch_parameters, batch_data);
+		}
+	}
+	
+	else {
+		let data: SpriteData = apply_palette(&parameters, data);
+		write_sprite(parameters, data);
+	}
+}
+
+
+// Reads a sprite and applies every pixel-side operation
+pub fn load_sprite(parameters: &Parameters) -> SpriteData {
+	let mut data: SpriteData = sprite_get::get_sprite(&parameters.source_path, parameters.source_format);
+	
 	if data.width == 0 || data.height == 0 {
-		return;
+		return SpriteData::default();
 	}
 	
 	// Trim padding
@@ -141,14 +263,22 @@ fn process_file(parameters: Parameters) {
 		}
 	}
 	
-	// -force-4bpp / -force-8bpp
+	// Remap
+	if !parameters.remap_table.is_empty() {
+		data.pixels = sprite_transform::remap_indices(data.pixels, &parameters.remap_table);
+	}
+	
+	// -force-1bpp / -force-2bpp / -force-4bpp / -force-8bpp
 	if parameters.forced_bit_depth {
 		data.bit_depth = parameters.bit_depth as u16;
 	}
-	else {
-		data.bit_depth = std::cmp::max(data.bit_depth, 4);
-	}
 	
+	return data;
+}
+
+
+// Builds the output palette and applies every palette-side operation
+pub fn apply_palette(parameters: &Parameters, mut data: SpriteData) -> SpriteData {
 	let mut temp_palette: Vec<u8> = Vec::new();
 	let color_count: usize = 2usize.pow(data.bit_depth as u32);
 	let alpha_processing: bool;
@@ -247,30 +377,139 @@ fn process_file(parameters: Parameters) {
 	// Pass result to data.
 	data.palette = temp_palette;
 	
+	// -clut-swizzle, 16-color CLUTs aren't swizzled
+	if parameters.clut_swizzle && !data.palette.is_empty() {
+		if data.palette.len() / 4 > 16 {
+			data.palette = sprite_transform::swizzle_palette(&data.palette);
+		}
+		
+		else {
+			println!("Warning: Will not -clut-swizzle as palette only has {} colors", data.palette.len() / 4);
+			println!("\tFile: {}", parameters.source_path.display());
+		}
+	}
+	
+	// -ps2-alpha. BIN palettes and the default +R alpha use PS2 alpha, -opaque and
+	// alpha stored in other formats use PC alpha
+	if parameters.ps2_alpha && !data.palette.is_empty() {
+		let palette_ps2: bool;
+		if parameters.opaque {
+			palette_ps2 = false;
+		}
+		else if parameters.palette_transfer {
+			palette_ps2 = parameters.source_format == SpriteFormat::BIN || data.metadata.alpha_filled;
+		}
+		else {
+			palette_ps2 = true;
+		}
+		
+		let target_ps2: bool = parameters.target_format == SpriteFormat::BIN;
+		
+		if palette_ps2 && !target_ps2 {
+			data.palette = sprite_transform::alpha_to_pc(&data.palette);
+		}
+		
+		else if !palette_ps2 && target_ps2 {
+			data.palette = sprite_transform::alpha_to_ps2(&data.palette);
+		}
+	}
+	
+	// -reindex-pal
+	if parameters.reindex_palette && !data.palette.is_empty() {
+		data.palette = sprite_transform::reindex_palette(&data.palette);
+	}
+	
+	// -remap-pal
+	if parameters.remap_palette && !data.palette.is_empty() {
+		data.palette = sprite_transform::remap_palette(&data.palette, &parameters.remap_table);
+	}
+	
+	// -compact-4bpp
+	if parameters.compact_4bpp && data.bit_depth > 4 {
+		let used_indices: Vec<u8> = sprite_transform::get_compact_indices(&data.pixels);
+		let colliding_indices: Vec<u8> = sprite_transform::get_colliding_indices(&used_indices, 16);
+		
+		if !colliding_indices.is_empty() {
+			let colliding: Vec<String> = colliding_indices.iter().map(|index| index.to_string()).collect();
+			println!("Warning: Will not -compact-4bpp as sprite uses {} colors (maximum 16)", used_indices.len());
+			println!("\tIndices that would collide: {}", colliding.join(", "));
+			println!("\tFile: {}", parameters.source_path.display());
+		}
+		
+		else {
+			data.pixels = sprite_transform::compact_indices(data.pixels, &used_indices);
+			
+			if !data.palette.is_empty() {
+				data.palette = sprite_transform::compact_palette(&data.palette, &used_indices, 16);
+			}
+			
+			data.bit_depth = 4;
+		}
+	}
+	
+	return data;
+}
+
+
+fn write_sprite(parameters: Parameters, data: SpriteData) {
 	match parameters.target_format {
 		SpriteFormat::PNG => sprite_make::make_png(parameters, data),
 		SpriteFormat::RAW => sprite_make::make_raw(parameters, data),
 		SpriteFormat::BIN => sprite_make::make_bin(parameters, data),
 		SpriteFormat::BMP => sprite_make::make_bmp(parameters, data),
-		_ => println!("main::process_file() error: Invalid target format provided"),
+		SpriteFormat::GIF => sprite_make::make_gif(parameters, data),
+		SpriteFormat::TGA => sprite_make::make_tga(parameters, data),
+		SpriteFormat::PCX => sprite_make::make_pcx(parameters, data),
+		_ => println!("main::write_sprite() error: Invalid target format provided"),
 	}
 }
 
 
-fn type_matches(extension: Option<&OsStr>, format: SpriteFormat) -> bool {
+// Sorted list of every file in a directory
+pub fn get_directory_files(source_path: &PathBuf) -> Vec<PathBuf> {
+	let mut file_list: Vec<PathBuf> = Vec::new();
+	
+	match source_path.read_dir() {
+		Ok(directory_items) => {
+			for item in directory_items {
+				match item {
+					Ok(entry) => file_list.push(entry.path()),
+					_ => (),
+				}
+			}
+		},
+		
+		_ => println!("main::get_directory_files() error: Could not read source path"),
+	}
+	
+	file_list.sort();
+	return file_list;
+}
+
+
+pub fn get_format(extension: Option<&OsStr>) -> SpriteFormat {
 	match extension {
 		Some(os_str) => match os_str.to_ascii_lowercase().to_str() {
-			Some("png") => return format == SpriteFormat::PNG,
-			Some("raw") => return format == SpriteFormat::RAW,
-			Some("bin") => return format == SpriteFormat::BIN,
-			Some("bmp") => return format == SpriteFormat::BMP,
-			_ => return false,
+			Some("png") => return SpriteFormat::PNG,
+			Some("raw") => return SpriteFormat::RAW,
+			Some("bin") => return SpriteFormat::BIN,
+			Some("bmp") => return SpriteFormat::BMP,
+			Some("gif") => return SpriteFormat::GIF,
+			Some("tga") => return SpriteFormat::TGA,
+			Some("pcx") => return SpriteFormat::PCX,
+			Some("act") => return SpriteFormat::ACT,
+			_ => return SpriteFormat::NONE,
 		},
-		_ => return false,
+		_ => return SpriteFormat::NONE,
 	}
 }
 
 
+pub fn type_matches(extension: Option<&OsStr>, format: SpriteFormat) -> bool {
+	return get_format(extension) == format;
+}
+
+
 fn process_directory_thread(pathbuf_vec: Vec<PathBuf>, mut parameters: Parameters, start_at: u16) -> usize {
 	let mut file_number: u16 = parameters.hash_value + start_at;
 	let mut processed_count: usize = 0;
@@ -0,0 +1,344 @@
+use std::io::{Write, BufWriter};
+use std::fs::File;
+
+use crate::{
+	PathBuf,
+	Parameters,
+	SpriteData,
+	SpriteFormat,
+	shared_types::ReportMode,
+	get_directory_files,
+	type_matches,
+	sprite_get,
+	sprite_make,
+	sprite_transform,
+};
+
+const ENTRIES_PER_LINE: usize = 8;
+
+
+struct SpriteUsage {
+	file: String,
+	bit_depth: u16,
+	histogram: [usize; 256],
+}
+
+
+pub fn report(parameters: Parameters) {
+	let file_list: Vec<PathBuf>;
+	
+	if parameters.directory_mode {
+		file_list = get_directory_files(&parameters.source_path);
+	}
+	
+	else {
+		file_list = vec![parameters.source_path.clone()];
+	}
+	
+	// Gather usage
+	let mut usage_list: Vec<SpriteUsage> = Vec::new();
+	
+	for file in file_list {
+		if !type_matches(file.extension(), parameters.source_format) {
+			continue;
+		}
+		
+		let format: SpriteFormat = parameters.source_format;
+		
+		let mut data: SpriteData = sprite_get::get_sprite(&file, format);
+		
+		if data.width == 0 || data.height == 0 {
+			continue;
+		}
+		
+		data.pixels = sprite_transform::trim_padding(data.pixels, data.width as usize, data.height as usize);
+		
+		let mut histogram: [usize; 256] = [0; 256];
+		for pixel in 0..data.pixels.len() {
+			histogram[data.pixels[pixel] as usize] += 1;
+		}
+		
+		usage_list.push(SpriteUsage {
+			file: file.display().to_string(),
+			bit_depth: data.bit_depth,
+			histogram: histogram,
+		});
+	}
+	
+	if usage_list.is_empty() {
+		println!("No sprites to report on.");
+		return;
+	}
+	
+	// Totals
+	let mut total: [usize; 256] = [0; 256];
+	let mut max_bit_depth: u16 = 4;
+	let mut out_of_range: Vec<String> = Vec::new();
+	
+	for usage in &usage_list {
+		for index in 0..256 {
+			total[index] += usage.histogram[index];
+		}
+		
+		max_bit_depth = std::cmp::max(max_bit_depth, usage.bit_depth);
+		
+		// Flagged 4bpp (or less) but uses indices a 4bpp sprite can't hold
+		if usage.bit_depth <= 4 && usage.histogram[16..].iter().any(|count| *count > 0) {
+			out_of_range.push(usage.file.clone());
+		}
+	}
+	
+	let color_range: usize = 2usize.pow(max_bit_depth as u32);
+	let mut unused: Vec<usize> = Vec::new();
+	
+	for index in 0..color_range {
+		if total[index] == 0 {
+			unused.push(index);
+		}
+	}
+	
+	match parameters.report_mode {
+		ReportMode::TEXT => print_text(&usage_list, &total, &unused, &out_of_range),
+		ReportMode::CSV => write_report(&parameters, "csv", make_csv(&usage_list, &total, &unused, &out_of_range)),
+		ReportMode::JSON => write_report(&parameters, "json", make_json(&usage_list, &total, &unused, &out_of_range)),
+		_ => (),
+	}
+}
+
+
+fn format_histogram(histogram: &[usize; 256]) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+	let mut entries: Vec<String> = Vec::new();
+	
+	for index in 0..256 {
+		if histogram[index] > 0 {
+			entries.push(format!("{:>3}: {:<8}", index, histogram[index]));
+		}
+	}
+	
+	for chunk in entries.chunks(ENTRIES_PER_LINE) {
+		lines.push(chunk.join(" ").trim_end().to_string());
+	}
+	
+	return lines;
+}
+
+
+fn format_ranges(indices: &Vec<usize>) -> String {
+	let mut ranges: Vec<String> = Vec::new();
+	let mut position: usize = 0;
+	
+	while position < indices.len() {
+		let start: usize = indices[position];
+		let mut end: usize = start;
+		
+		while position + 1 < indices.len() && indices[position + 1] == end + 1 {
+			position += 1;
+			end += 1;
+		}
+		
+		if start == end {
+			ranges.push(format!("{}", start));
+		}
+		else {
+			ranges.push(format!("{}-{}", start, end));
+		}
+		
+		position += 1;
+	}
+	
+	return ranges.join(", ");
+}
+
+
+fn color_count(histogram: &[usize; 256]) -> usize {
+	return histogram.iter().filter(|count| **count > 0).count();
+}
+
+
+fn print_text(usage_list: &Vec<SpriteUsage>, total: &[usize; 256], unused: &Vec<usize>, out_of_range: &Vec<String>) {
+	for usage in usage_list {
+		println!("{} ({} bpp, {} colors)", usage.file, usage.bit_depth, color_count(&usage.histogram));
+		
+		for line in format_histogram(&usage.histogram) {
+			println!("    {}", line);
+		}
+		
+		println!();
+	}
+	
+	println!("Total ({} sprites, {} colors)", usage_list.len(), color_count(total));
+	
+	for line in format_histogram(total) {
+		println!("    {}", line);
+	}
+	
+	println!();
+	
+	if unused.is_empty() {
+		println!("Unused indices: none");
+	}
+	else {
+		println!("Unused indices: {}", format_ranges(unused));
+	}
+	
+	if out_of_range.is_empty() {
+		println!("4 bpp sprites using indices above 15: none");
+	}
+	else {
+		println!("4 bpp sprites using indices above 15:");
+		for file in out_of_range {
+			println!("    {}", file);
+		}
+	}
+}
+
+
+fn escape_csv(value: &str) -> String {
+	return format!("\"{}\"", value.replace('"', "\"\""));
+}
+
+
+fn escape_json(value: &str) -> String {
+	return format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
+
+// One row per sprite plus the total. Sprites get an extra column flagging 4bpp ones
+// using indices above 15, and a last row marks every unused index with a 1.
+fn make_csv(usage_list: &Vec<SpriteUsage>, total: &[usize; 256], unused: &Vec<usize>, out_of_range: &Vec<String>) -> String {
+	let mut lines: Vec<String> = Vec::new();
+	let mut header: Vec<String> = vec!["file".to_string(), "bit_depth".to_string()];
+	
+	for index in 0..256 {
+		header.push(format!("{}", index));
+	}
+	
+	header.push("out_of_range_4bpp".to_string());
+	lines.push(header.join(","));
+	
+	for usage in usage_list {
+		let mut row: Vec<String> = vec![escape_csv(&usage.file), format!("{}", usage.bit_depth)];
+		row.extend(usage.histogram.iter().map(|count| count.to_string()));
+		row.push(format!("{}", out_of_range.contains(&usage.file) as u8));
+		lines.push(row.join(","));
+	}
+	
+	let mut total_row: Vec<String> = vec!["total".to_string(), String::new()];
+	total_row.extend(total.iter().map(|count| count.to_string()));
+	total_row.push(String::new());
+	lines.push(total_row.join(","));
+	
+	let mut unused_row: Vec<String> = vec!["unused".to_string(), String::new()];
+	for index in 0..256 {
+		if unused.contains(&index) {
+			unused_row.push("1".to_string());
+		}
+		else {
+			unused_row.push(String::new());
+		}
+	}
+	unused_row.push(String::new());
+	lines.push(unused_row.join(","));
+	
+	return lines.join("\n") + "\n";
+}
+
+
+fn make_json_histogram(histogram: &[usize; 256]) -> String {
+	let mut entries: Vec<String> = Vec::new();
+	
+	for index in 0..256 {
+		if histogram[index] > 0 {
+			entries.push(format!("\"{}\": {}", index, histogram[index]));
+		}
+	}
+	
+	return format!("{{{}}}", entries.join(", "));
+}
+
+
+fn make_json(usage_list: &Vec<SpriteUsage>, total: &[usize; 256], unused: &Vec<usize>, out_of_range: &Vec<String>) -> String {
+	let mut sprites: Vec<String> = Vec::new();
+	
+	for usage in usage_list {
+		sprites.push(format!(
+			"\t\t{{\"file\": {}, \"bit_depth\": {}, \"histogram\": {}}}",
+			escape_json(&usage.file),
+			usage.bit_depth,
+			make_json_histogram(&usage.histogram),
+		));
+	}
+	
+	let unused_list: Vec<String> = unused.iter().map(|index| index.to_string()).collect();
+	let out_of_range_list: Vec<String> = out_of_range.iter().map(|file| escape_json(file)).collect();
+	
+	let mut json: String = String::from("{\n");
+	json += &format!("\t\"sprites\": [\n{}\n\t],\n", sprites.join(",\n"));
+	json += &format!("\t\"total\": {},\n", make_json_histogram(total));
+	json += &format!("\t\"unused\": [{}],\n", unused_list.join(", "));
+	json += &format!("\t\"out_of_range_4bpp\": [{}]\n", out_of_range_list.join(", "));
+	json += "}\n";
+	
+	return json;
+}
+
+
+fn write_report(parameters: &Parameters, extension: &str, contents: String) {
+	let mut target_path: PathBuf = parameters.target_path.clone();
+	target_path.push("palette_report");
+	target_path.set_extension(extension);
+	
+	if sprite_make::overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+	
+	let report_file: File;
+	match File::create(&target_path) {
+		Ok(file) => report_file = file,
+		_ => {
+			println!("palette_report::write_report() error: Could not create report file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	let mut buffer = BufWriter::new(report_file);
+	let _ = buffer.write_all(contents.as_bytes());
+	
+	match buffer.flush() {
+		Ok(_) => println!("Wrote report to '{}'", target_path.display()),
+		_ => {
+			println!("palette_report::write_report() error: Could not write report file");
+			println!("\tFile: {}", &target_path.display());
+		},
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn csv_lists_unused_and_out_of_range() {
+		let mut histogram: [usize; 256] = [0; 256];
+		histogram[1] = 5;
+		histogram[20] = 2;
+		
+		let usage_list: Vec<SpriteUsage> = vec![SpriteUsage { file: "a.bin".to_string(), bit_depth: 4, histogram: histogram }];
+		let unused: Vec<usize> = vec![0, 2];
+		let out_of_range: Vec<String> = vec!["a.bin".to_string()];
+		
+		let csv: String = make_csv(&usage_list, &histogram, &unused, &out_of_range);
+		let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+		
+		assert_eq!(rows.len(), 4);
+		assert!(rows.iter().all(|row| row.len() == 259));
+		assert_eq!(rows[0][258], "out_of_range_4bpp");
+		assert_eq!(rows[1][258], "1");
+		assert_eq!(rows[2][0], "total");
+		assert_eq!(rows[3][0], "unused");
+		assert_eq!(&rows[3][2..6], &["1", "", "1", ""]);
+	}
+}
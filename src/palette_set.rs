@@ -0,0 +1,404 @@
+use std::fs;
+use std::fs::File;
+use std::ffi::OsString;
+use std::io::{BufWriter, Write};
+
+use crate::{
+	PathBuf,
+	Parameters,
+	shared_types::PaletteSetMode,
+	sprite_get,
+	sprite_make,
+	sprite_transform,
+};
+
+// A palette set is every palette slot of a character stored back to back, each one
+// laid out like a BIN's embedded palette (RGBA, 4 bytes per color). Sets of 16-color
+// palettes are told apart from 256-color ones by their size. Neither has been
+// checked against the game's own files. Anything past the last
+// whole palette is kept as it is when rebuilding. -palset-16 covers sets of 16-color
+// palettes whose size happens to be a multiple of 256 colors.
+const SET_COLORS: usize = 256;
+const SET_COLORS_SMALL: usize = 16;
+
+
+pub fn palette_set(parameters: Parameters) -> usize {
+	let set_data: Vec<u8>;
+	match fs::read(&parameters.source_path) {
+		Ok(value) => set_data = value,
+		_ => {
+			println!("palette_set::palette_set() error: Palette set file read error");
+			println!("\tSkipped: {}", parameters.source_path.display());
+			return 0;
+		},
+	}
+	
+	let palette_size: usize = get_palette_size(set_data.len(), parameters.palette_set_small);
+	let palette_count: usize = set_data.len() / palette_size;
+	
+	if palette_count == 0 {
+		println!("palette_set::palette_set() error: File is smaller than a single palette");
+		println!("\tSkipped: {}", parameters.source_path.display());
+		return 0;
+	}
+	
+	if set_data.len() % palette_size != 0 {
+		println!("Warning: Palette set has {} bytes past its last palette, they will be left as they are", set_data.len() % palette_size);
+		println!("\tFile: {}", parameters.source_path.display());
+	}
+	
+	match parameters.palette_set_mode {
+		PaletteSetMode::ACT | PaletteSetMode::GPL => return export(&parameters, &set_data, palette_size, palette_count),
+		PaletteSetMode::REBUILD => return rebuild(&parameters, set_data, palette_size, palette_count),
+		PaletteSetMode::NONE => return 0,
+	}
+}
+
+
+// Bytes per palette in a set of set_length bytes
+fn get_palette_size(set_length: usize, small: bool) -> usize {
+	if small {
+		return 4 * SET_COLORS_SMALL;
+	}
+	
+	if set_length % (4 * SET_COLORS) == 0 || set_length < 4 * SET_COLORS_SMALL {
+		return 4 * SET_COLORS;
+	}
+	
+	return 4 * SET_COLORS_SMALL;
+}
+
+
+// Palettes exported from a set are named after it and numbered by slot
+fn slot_name(parameters: &Parameters, slot: usize, extension: &str) -> OsString {
+	return sprite_make::stem_with_suffix(&parameters.source_path, &format!("_{:02}.{}", slot, extension));
+}
+
+
+fn export(parameters: &Parameters, set_data: &Vec<u8>, palette_size: usize, palette_count: usize) -> usize {
+	let mut exported_count: usize = 0;
+	
+	let extension: &str;
+	if parameters.palette_set_mode == PaletteSetMode::GPL {
+		extension = "gpl";
+	}
+	else {
+		extension = "act";
+	}
+	
+	for slot in 0..palette_count {
+		let mut target_path: PathBuf = parameters.target_path.clone();
+		target_path.push(slot_name(parameters, slot, extension));
+		
+		if sprite_make::overwrite_blocked(&target_path, parameters.overwrite) {
+			continue;
+		}
+		
+		let mut palette: Vec<u8> = set_data[slot * palette_size..(slot + 1) * palette_size].to_vec();
+		
+		if parameters.ps2_alpha {
+			palette = sprite_transform::alpha_to_pc(&palette);
+		}
+		
+		if parameters.clut_swizzle && palette_size > 4 * SET_COLORS_SMALL {
+			palette = sprite_transform::swizzle_palette(&palette);
+		}
+		
+		let file_data: Vec<u8>;
+		
+		if parameters.palette_set_mode == PaletteSetMode::GPL {
+			let file_stem: String = target_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+			file_data = gpl_bytes(&palette, &file_stem);
+		}
+		else {
+			file_data = act_bytes(&palette);
+		}
+		
+		let palette_file: File;
+		match File::create(&target_path) {
+			Ok(file) => palette_file = file,
+			_ => {
+				println!("palette_set::export() error: Could not create target palette file");
+				println!("\tSkipped: {}", &target_path.display());
+				continue;
+			},
+		}
+		
+		let ref mut buffer = BufWriter::new(palette_file);
+		let _ = buffer.write_all(&file_data);
+		let _ = buffer.flush();
+		
+		if parameters.verbose {
+			println!("Exported '{}'", target_path.display());
+		}
+		
+		exported_count += 1;
+	}
+	
+	return exported_count;
+}
+
+
+// Extended ACT, which can only mark one color as transparent
+fn act_bytes(palette: &[u8]) -> Vec<u8> {
+	let color_count: usize = palette.len() / 4;
+	let mut act_data: Vec<u8> = vec![0; 0x300];
+	let mut transparent_index: u16 = 0xFFFF;
+	
+	for color in 0..color_count {
+		act_data[3 * color + 0] = palette[4 * color + 0];
+		act_data[3 * color + 1] = palette[4 * color + 1];
+		act_data[3 * color + 2] = palette[4 * color + 2];
+		
+		if transparent_index == 0xFFFF && palette[4 * color + 3] == 0x00 {
+			transparent_index = color as u16;
+		}
+	}
+	
+	act_data.extend_from_slice(&(color_count as u16).to_be_bytes());
+	act_data.extend_from_slice(&transparent_index.to_be_bytes());
+	
+	return act_data;
+}
+
+
+// GIMP palette, alpha goes in each color's name so it survives a round trip
+fn gpl_bytes(palette: &[u8], name: &str) -> Vec<u8> {
+	let mut gpl_text: String = String::new();
+	
+	gpl_text.push_str("GIMP Palette\n");
+	gpl_text.push_str(&format!("Name: {}\n", name));
+	gpl_text.push_str("Columns: 16\n");
+	gpl_text.push_str("#\n");
+	
+	for color in 0..palette.len() / 4 {
+		gpl_text.push_str(&format!(
+			"{:3} {:3} {:3}\tIndex {} alpha 0x{:02X}\n",
+			palette[4 * color + 0], palette[4 * color + 1], palette[4 * color + 2], color, palette[4 * color + 3]
+		));
+	}
+	
+	return gpl_text.into_bytes();
+}
+
+
+// Returns the palette and whether every color had its alpha in its name
+fn read_gpl(source_file: &PathBuf) -> Option<(Vec<u8>, bool)> {
+	let gpl_text: String;
+	match fs::read_to_string(source_file) {
+		Ok(value) => gpl_text = value,
+		_ => {
+			println!("palette_set::read_gpl() error: GPL file read error");
+			println!("\tSkipped: {}", source_file.display());
+			return None;
+		},
+	}
+	
+	let mut lines = gpl_text.lines();
+	
+	if lines.next().map(|line| line.trim()) != Some("GIMP Palette") {
+		println!("palette_set::read_gpl() error: File is not a GIMP palette");
+		println!("\tSkipped: {}", source_file.display());
+		return None;
+	}
+	
+	let mut palette: Vec<u8> = Vec::new();
+	let mut has_alpha: bool = true;
+	let mut rgba_channels: bool = false;
+	
+	for line in lines {
+		let line: &str = line.trim();
+		
+		// Some editors write a fourth alpha column instead
+		if line.starts_with("Channels:") {
+			rgba_channels = line.contains("RGBA");
+			continue;
+		}
+		
+		if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+			continue;
+		}
+		
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		let mut rgb: [u8; 3] = [0; 3];
+		
+		for channel in 0..3 {
+			match fields.get(channel).map(|field| field.parse::<u8>()) {
+				Some(Ok(value)) => rgb[channel] = value,
+				_ => {
+					println!("palette_set::read_gpl() error: Could not read color '{}'", line);
+					println!("\tSkipped: {}", source_file.display());
+					return None;
+				},
+			}
+		}
+		
+		palette.extend_from_slice(&rgb);
+		
+		if rgba_channels {
+			match fields.get(3).map(|field| field.parse::<u8>()) {
+				Some(Ok(value)) => palette.push(value),
+				_ => {
+					has_alpha = false;
+					palette.push(0xFF);
+				},
+			}
+			
+			continue;
+		}
+		
+		match fields.iter().position(|field| *field == "alpha") {
+			Some(position) => match fields.get(position + 1).map(|field| u8::from_str_radix(field.trim_start_matches("0x"), 16)) {
+				Some(Ok(value)) => palette.push(value),
+				_ => {
+					has_alpha = false;
+					palette.push(0xFF);
+				},
+			},
+			
+			None => {
+				has_alpha = false;
+				palette.push(0xFF);
+			},
+		}
+	}
+	
+	return Some((palette, has_alpha));
+}
+
+
+fn rebuild(parameters: &Parameters, mut set_data: Vec<u8>, palette_size: usize, palette_count: usize) -> usize {
+	let mut target_path: PathBuf = parameters.target_path.clone();
+	target_path.push(parameters.source_path.file_name().unwrap());
+	
+	if sprite_make::overwrite_blocked(&target_path, parameters.overwrite) {
+		return 0;
+	}
+	
+	let mut replaced_count: usize = 0;
+	
+	for slot in 0..palette_count {
+		let mut act_path: PathBuf = parameters.palette_set_edits.clone();
+		act_path.push(slot_name(parameters, slot, "act"));
+		let mut gpl_path: PathBuf = parameters.palette_set_edits.clone();
+		gpl_path.push(slot_name(parameters, slot, "gpl"));
+		
+		// GPLs written by ghoul carry alpha, ACTs keep the set's own
+		let mut edited_palette: Vec<u8>;
+		let has_alpha: bool;
+		let edited_path: PathBuf;
+		
+		if gpl_path.is_file() {
+			match read_gpl(&gpl_path) {
+				Some((palette, alpha)) if parameters.ps2_alpha => {
+					edited_palette = sprite_transform::alpha_to_ps2(&palette);
+					has_alpha = alpha;
+				},
+				
+				Some((palette, alpha)) => {
+					edited_palette = palette;
+					has_alpha = alpha;
+				},
+				
+				None => continue,
+			}
+			
+			edited_path = gpl_path;
+		}
+		
+		else if act_path.is_file() {
+			edited_palette = sprite_get::get_act(&act_path).palette;
+			has_alpha = false;
+			edited_path = act_path;
+		}
+		
+		// Slots without an edited palette are kept as they are
+		else {
+			continue;
+		}
+		
+		// Back to the order the set was exported from
+		if parameters.clut_swizzle && palette_size > 4 * SET_COLORS_SMALL {
+			edited_palette = sprite_transform::swizzle_palette(&edited_palette);
+		}
+		
+		let slot_palette: &mut [u8] = &mut set_data[slot * palette_size..(slot + 1) * palette_size];
+		let color_count: usize = std::cmp::min(edited_palette.len(), palette_size) / 4;
+		
+		if edited_palette.len() / 4 != palette_size / 4 {
+			println!(
+				"Warning: Palette has {} colors but the set's palettes have {}, replacing the first {}",
+				edited_palette.len() / 4, palette_size / 4, color_count
+			);
+			println!("\tFile: {}", edited_path.display());
+		}
+		
+		for color in 0..color_count {
+			slot_palette[4 * color + 0] = edited_palette[4 * color + 0];
+			slot_palette[4 * color + 1] = edited_palette[4 * color + 1];
+			slot_palette[4 * color + 2] = edited_palette[4 * color + 2];
+			
+			if has_alpha {
+				slot_palette[4 * color + 3] = edited_palette[4 * color + 3];
+			}
+		}
+		
+		if parameters.verbose {
+			println!("Replaced slot {:02} with '{}'", slot, edited_path.display());
+		}
+		
+		replaced_count += 1;
+	}
+	
+	let set_file: File;
+	match File::create(&target_path) {
+		Ok(file) => set_file = file,
+		_ => {
+			println!("palette_set::rebuild() error: Could not create target palette set file");
+			println!("\tSkipped: {}", &target_path.display());
+			return 0;
+		},
+	}
+	
+	let ref mut buffer = BufWriter::new(set_file);
+	let _ = buffer.write_all(&set_data);
+	let _ = buffer.flush();
+	
+	return replaced_count;
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_file::TempFile;
+	
+	#[test]
+	fn palette_size_from_set_length() {
+		assert_eq!(get_palette_size(4 * 256 * 3, false), 4 * 256);
+		assert_eq!(get_palette_size(4 * 16 * 5, false), 4 * 16);
+		assert_eq!(get_palette_size(4 * 256 * 3, true), 4 * 16);
+		
+		// Too small for either, reported as smaller than a palette
+		assert_eq!(get_palette_size(10, false), 4 * 256);
+	}
+	
+	#[test]
+	fn act_bytes_layout() {
+		let palette: Vec<u8> = vec![1, 2, 3, 0x80, 4, 5, 6, 0x00, 7, 8, 9, 0x00];
+		let act_data: Vec<u8> = act_bytes(&palette);
+		
+		assert_eq!(act_data.len(), 0x304);
+		assert_eq!(&act_data[0..9], &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+		assert_eq!(&act_data[0x300..], &[0, 3, 0, 1]);
+	}
+	
+	#[test]
+	fn gpl_round_trip_keeps_alpha() {
+		let palette: Vec<u8> = (0..4 * 16).map(|byte| (byte * 5) as u8).collect();
+		
+		let gpl_file: TempFile = TempFile::new("set.gpl", &gpl_bytes(&palette, "set"));
+		
+		assert_eq!(read_gpl(&gpl_file), Some((palette, true)));
+	}
+}
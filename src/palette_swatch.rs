@@ -0,0 +1,256 @@
+use std::io::BufWriter;
+use std::fs::File;
+
+use crate::{
+	PathBuf,
+	Parameters,
+	SpriteData,
+	SpriteFormat,
+	shared_types::SwatchMode,
+	get_directory_files,
+	type_matches,
+	sprite_get,
+	sprite_make,
+};
+
+const SWATCH_SIZE: usize = 24;
+const GRID_WIDTH: usize = 1;
+const GRID_COLOR: [u8; 3] = [0x40, 0x40, 0x40];
+const CHECKER_SIZE: usize = 4;
+const CHECKER_LIGHT: u8 = 0xFF;
+const CHECKER_DARK: u8 = 0xCC;
+
+// 3x5 digits, one row per byte, high bit first
+const DIGIT_WIDTH: usize = 3;
+const DIGIT_HEIGHT: usize = 5;
+const DIGITS: [[u8; 5]; 10] = [
+	[0b111, 0b101, 0b101, 0b101, 0b111],
+	[0b010, 0b110, 0b010, 0b010, 0b111],
+	[0b111, 0b001, 0b111, 0b100, 0b111],
+	[0b111, 0b001, 0b111, 0b001, 0b111],
+	[0b101, 0b101, 0b111, 0b001, 0b001],
+	[0b111, 0b100, 0b111, 0b001, 0b111],
+	[0b111, 0b100, 0b111, 0b101, 0b111],
+	[0b111, 0b001, 0b010, 0b010, 0b010],
+	[0b111, 0b101, 0b111, 0b101, 0b111],
+	[0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+
+pub fn swatch(parameters: Parameters) -> usize {
+	let file_list: Vec<PathBuf>;
+	
+	if parameters.directory_mode {
+		file_list = get_directory_files(&parameters.source_path);
+	}
+	
+	else {
+		file_list = vec![parameters.source_path.clone()];
+	}
+	
+	let mut processed_count: usize = 0;
+	
+	for file in file_list {
+		if !type_matches(file.extension(), parameters.source_format) {
+			continue;
+		}
+		
+		let format: SpriteFormat = parameters.source_format;
+		
+		if parameters.verbose {
+			println!("Processing '{}'", file.display());
+		}
+		
+		let data: SpriteData = sprite_get::get_sprite(&file, format);
+		
+		if data.palette.is_empty() {
+			println!("Warning: Will not make swatch as source contains no palette");
+			println!("\tSkipped: {}", file.display());
+			continue;
+		}
+		
+		let mut file_parameters: Parameters = parameters.clone();
+		file_parameters.source_path = file;
+		file_parameters.target_suffix = String::from("_swatch");
+		
+		// BIN palettes and default +R alpha filled in by the reader use PS2 alpha
+		let ps2_alpha: bool = format == SpriteFormat::BIN || data.metadata.alpha_filled;
+		make_swatch(file_parameters, data.palette, ps2_alpha);
+		processed_count += 1;
+	}
+	
+	return processed_count;
+}
+
+
+fn make_swatch(parameters: Parameters, palette: Vec<u8>, ps2_alpha: bool) {
+	let target_path: PathBuf = sprite_make::get_target_path(&parameters, "png");
+	
+	if sprite_make::overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+	
+	let (image, image_size) = swatch_image(palette, ps2_alpha, parameters.swatch_mode);
+	
+	let png_file: File;
+	match File::create(&target_path) {
+		Ok(file) => png_file = file,
+		_ => {
+			println!("palette_swatch::make_swatch() error: Could not create target PNG file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	let buffer = BufWriter::new(png_file);
+	let mut encoder = png::Encoder::new(buffer, image_size as u32, image_size as u32);
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	
+	let mut writer = encoder.write_header().expect("palette_swatch::make_swatch() error: Could not write PNG header");
+	writer.write_image_data(&image).unwrap();
+}
+
+
+// Swatch grid as 8-bit RGB, and its width (same as its height)
+fn swatch_image(mut palette: Vec<u8>, ps2_alpha: bool, swatch_mode: SwatchMode) -> (Vec<u8>, usize) {
+	// 16 colors as 4x4, anything bigger as 16x16
+	let color_count: usize;
+	if palette.len() / 4 <= 16 {
+		color_count = 16;
+	}
+	else {
+		color_count = 256;
+	}
+	
+	// Missing colors are opaque black
+	while palette.len() < color_count * 4 {
+		palette.extend_from_slice(&[0x00, 0x00, 0x00, 0xFF]);
+	}
+	
+	let columns: usize = (color_count as f32).sqrt() as usize;
+	let cell_size: usize = SWATCH_SIZE + GRID_WIDTH;
+	let image_size: usize = columns * cell_size + GRID_WIDTH;
+	
+	// RGB image, grid color by default
+	let mut image: Vec<u8> = Vec::with_capacity(image_size * image_size * 3);
+	for _pixel in 0..image_size * image_size {
+		image.extend_from_slice(&GRID_COLOR);
+	}
+	
+	for color in 0..color_count {
+		let origin_x: usize = (color % columns) * cell_size + GRID_WIDTH;
+		let origin_y: usize = (color / columns) * cell_size + GRID_WIDTH;
+		
+		// PS2 alpha is opaque at 0x80
+		let mut alpha: u32 = palette[4 * color + 3] as u32;
+		if ps2_alpha {
+			alpha = std::cmp::min(alpha * 0xFF / 0x80, 0xFF);
+		}
+		
+		for y in 0..SWATCH_SIZE {
+			for x in 0..SWATCH_SIZE {
+				// Blend over checkerboard
+				let checker: u32;
+				if ((x / CHECKER_SIZE) + (y / CHECKER_SIZE)) % 2 == 0 {
+					checker = CHECKER_LIGHT as u32;
+				}
+				else {
+					checker = CHECKER_DARK as u32;
+				}
+				
+				let position: usize = 3 * ((origin_y + y) * image_size + origin_x + x);
+				
+				for channel in 0..3 {
+					let value: u32 = palette[4 * color + channel] as u32;
+					image[position + channel] = ((value * alpha + checker * (0xFF - alpha)) / 0xFF) as u8;
+				}
+			}
+		}
+		
+		if swatch_mode == SwatchMode::LABELS {
+			draw_label(&mut image, image_size, origin_x + 1, origin_y + 1, color);
+		}
+	}
+	
+	return (image, image_size);
+}
+
+
+fn draw_label(image: &mut Vec<u8>, image_size: usize, origin_x: usize, origin_y: usize, index: usize) {
+	let label: String = index.to_string();
+	
+	// Background box keeps labels legible over any color
+	let box_width: usize = label.len() * (DIGIT_WIDTH + 1) + 1;
+	let box_height: usize = DIGIT_HEIGHT + 2;
+	
+	for y in 0..box_height {
+		for x in 0..box_width {
+			let position: usize = 3 * ((origin_y + y) * image_size + origin_x + x);
+			image[position..position + 3].copy_from_slice(&[0x00, 0x00, 0x00]);
+		}
+	}
+	
+	for (digit_number, digit) in label.bytes().enumerate() {
+		let glyph: [u8; 5] = DIGITS[(digit - b'0') as usize];
+		let digit_x: usize = origin_x + 1 + digit_number * (DIGIT_WIDTH + 1);
+		
+		for y in 0..DIGIT_HEIGHT {
+			for x in 0..DIGIT_WIDTH {
+				if (glyph[y] >> (DIGIT_WIDTH - 1 - x)) & 1 == 1 {
+					let position: usize = 3 * ((origin_y + 1 + y) * image_size + digit_x + x);
+					image[position..position + 3].copy_from_slice(&[0xFF, 0xFF, 0xFF]);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	// Bottom right pixel of a color's cell, clear of its label and on a light checker square
+	fn cell_pixel(image: &Vec<u8>, image_size: usize, color: usize) -> [u8; 3] {
+		let columns: usize = (image_size - GRID_WIDTH) / (SWATCH_SIZE + GRID_WIDTH);
+		let x: usize = (color % columns) * (SWATCH_SIZE + GRID_WIDTH) + GRID_WIDTH + SWATCH_SIZE - 1;
+		let y: usize = (color / columns) * (SWATCH_SIZE + GRID_WIDTH) + GRID_WIDTH + SWATCH_SIZE - 1;
+		let position: usize = 3 * (y * image_size + x);
+		return [image[position], image[position + 1], image[position + 2]];
+	}
+	
+	#[test]
+	fn swatch_opaque_colors() {
+		let palette: Vec<u8> = vec![
+			0, 0, 0, 0,
+			10, 20, 30, 0xFF,
+			200, 100, 0, 0x80,
+		];
+		
+		let (image, image_size) = swatch_image(palette, false, SwatchMode::LABELS);
+		
+		assert_eq!(image_size, 4 * (SWATCH_SIZE + GRID_WIDTH) + GRID_WIDTH);
+		assert_eq!(cell_pixel(&image, image_size, 0), [CHECKER_LIGHT; 3]);
+		assert_eq!(cell_pixel(&image, image_size, 1), [10, 20, 30]);
+		
+		// 0x80 is only half opaque on PC
+		assert_eq!(cell_pixel(&image, image_size, 2), [227, 177, 127]);
+	}
+	
+	#[test]
+	fn swatch_ps2_alpha_colors() {
+		let palette: Vec<u8> = vec![
+			0, 0, 0, 0,
+			10, 20, 30, 0x80,
+			200, 100, 0, 0x40,
+		];
+		
+		let (image, image_size) = swatch_image(palette, true, SwatchMode::GRID);
+		
+		assert_eq!(cell_pixel(&image, image_size, 0), [CHECKER_LIGHT; 3]);
+		assert_eq!(cell_pixel(&image, image_size, 1), [10, 20, 30]);
+		
+		// 0x40 is half opaque on PS2
+		assert_eq!(cell_pixel(&image, image_size, 2), [227, 177, 128]);
+	}
+}
@@ -2,7 +2,15 @@ use std::fs;
 use std::ffi::OsStr;
 
 use crate::{
+	shared_types::BmpHeader,
 	shared_types::HashMode,
+	shared_types::ReportMode,
+	shared_types::SwatchMode,
+	shared_types::ArchiveMode,
+	shared_types::PaletteSetMode,
+	bin_header,
+	bin_header::TextureExponent,
+	remap_table,
 	PathBuf,
 	Parameters,
 	SpriteFormat
@@ -14,7 +22,18 @@ enum ArgumentType {
 	OUTPUT,
 	FORMAT,
 	PALETTE,
+	REMAP,
+	REPORT,
+	SWATCH,
+	ANIMATE,
+	DELAY,
 	HASH,
+	TW,
+	TH,
+	HEADER,
+	ARCHIVE,
+	PALSET,
+	PALEDITS,
 }
 
 
@@ -26,12 +45,33 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 	let mut palette_transfer: bool = false;
 	let mut forced_bit_depth: bool = false;
 	let mut bit_depth: usize = 8;
+	let mut compact_4bpp: bool = false;
 	let mut as_rgb: bool = false;
 	let mut opaque: bool = false;
+	let mut ps2_alpha: bool = false;
+	let mut clut_swizzle: bool = false;
 	let mut hash_mode: HashMode = HashMode::GENERATE;
 	let mut hash_value: u16 = 0x0000;
+	let mut tw_override: Option<TextureExponent> = None;
+	let mut th_override: Option<TextureExponent> = None;
+	let mut fit_texture: bool = false;
 	let mut uncompressed: bool = false;
+	let mut rle: bool = false;
+	let mut bmp_header: BmpHeader = BmpHeader::CORE;
 	let mut reindex: bool = false;
+	let mut reindex_palette: bool = false;
+	let mut source_remap: &str = "";
+	let mut remap_palette: bool = false;
+	let mut report_mode: ReportMode = ReportMode::NONE;
+	let mut info_mode: bool = false;
+	let mut archive_mode: ArchiveMode = ArchiveMode::NONE;
+	let mut archive_sprites: PathBuf = PathBuf::new();
+	let mut palette_set_mode: PaletteSetMode = PaletteSetMode::NONE;
+	let mut palette_set_edits: PathBuf = PathBuf::new();
+	let mut palette_set_small: bool = false;
+	let mut swatch_mode: SwatchMode = SwatchMode::NONE;
+	let mut animate_format: SpriteFormat = SpriteFormat::NONE;
+	let mut frame_delay: u32 = 100;
 	let mut verbose: bool = false;
 	let mut overwrite: bool = false;
 	
@@ -84,6 +124,24 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 			
 			ArgumentType::FORMAT => {
 				match &this_argument.to_lowercase() as &str {
+					"gif" => {
+						target_format = SpriteFormat::GIF;
+						next_arg = ArgumentType::NONE;
+						continue;
+					},
+					
+					"tga" => {
+						target_format = SpriteFormat::TGA;
+						next_arg = ArgumentType::NONE;
+						continue;
+					},
+					
+					"pcx" => {
+						target_format = SpriteFormat::PCX;
+						next_arg = ArgumentType::NONE;
+						continue;
+					},
+					
 					"png" => {
 						target_format = SpriteFormat::PNG;
 						next_arg = ArgumentType::NONE;
@@ -109,7 +167,7 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 					},
 					
 					_ => {
-						println!("Unsupported output format '{}'. Supported formats: 'png', 'raw', 'bin', 'bmp'.", &args[argument]);
+						println!("Unsupported output format '{}'. Supported formats: 'png', 'raw', 'bin', 'bmp', 'gif', 'tga', 'pcx'.", &args[argument]);
 						return None;
 					},
 				}
@@ -121,6 +179,69 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 				continue;
 			},
 			
+			ArgumentType::REMAP => {
+				source_remap = &args[argument];
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::REPORT => {
+				match &this_argument.to_lowercase() as &str {
+					"text" => report_mode = ReportMode::TEXT,
+					"csv" => report_mode = ReportMode::CSV,
+					"json" => report_mode = ReportMode::JSON,
+					_ => {
+						println!("Unsupported report format '{}'. Supported formats: 'text', 'csv', 'json'.", &args[argument]);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::SWATCH => {
+				match &this_argument.to_lowercase() as &str {
+					"grid" => swatch_mode = SwatchMode::GRID,
+					"labels" => swatch_mode = SwatchMode::LABELS,
+					_ => {
+						println!("Unsupported swatch style '{}'. Supported styles: 'grid', 'labels'.", &args[argument]);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::ANIMATE => {
+				match &this_argument.to_lowercase() as &str {
+					"gif" => animate_format = SpriteFormat::GIF,
+					"apng" => animate_format = SpriteFormat::PNG,
+					_ => {
+						println!("Unsupported animation format '{}'. Supported formats: 'gif', 'apng'.", &args[argument]);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::DELAY => {
+				match &args[argument].parse::<u32>() {
+					Ok(value) => frame_delay = *value,
+					
+					_ => {
+						println!("Could not parse frame delay as milliseconds, defaulting to 100.");
+						frame_delay = 100;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
 			ArgumentType::HASH => {
 				match &args[argument].parse::<u16>() {
 					Ok(value) => hash_value = *value,
@@ -135,6 +256,88 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 				continue;
 			}
 			
+			ArgumentType::TW => {
+				match &args[argument].parse::<u16>() {
+					Ok(value) if bin_header::exponent_valid(TextureExponent(*value)) => tw_override = Some(TextureExponent(*value)),
+					
+					_ => {
+						println!("tw is a texture page width exponent and must be between 0 and {} (1 to {}px).", bin_header::TEXTURE_EXPONENT_MAX, 1 << bin_header::TEXTURE_EXPONENT_MAX);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::TH => {
+				match &args[argument].parse::<u16>() {
+					Ok(value) if bin_header::exponent_valid(TextureExponent(*value)) => th_override = Some(TextureExponent(*value)),
+					
+					_ => {
+						println!("th is a texture page height exponent and must be between 0 and {} (1 to {}px).", bin_header::TEXTURE_EXPONENT_MAX, 1 << bin_header::TEXTURE_EXPONENT_MAX);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::ARCHIVE => {
+				archive_sprites = PathBuf::from(&args[argument]);
+				
+				if !archive_sprites.is_dir() {
+					println!("Could not locate the directory of replacement sprites for -archive-repack, aborting operation.");
+					return None;
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::PALSET => {
+				match &this_argument.to_lowercase() as &str {
+					"act" => palette_set_mode = PaletteSetMode::ACT,
+					"gpl" => palette_set_mode = PaletteSetMode::GPL,
+					_ => {
+						println!("Unsupported palette export format '{}'. Supported formats: 'act', 'gpl'.", &args[argument]);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::PALEDITS => {
+				palette_set_edits = PathBuf::from(&args[argument]);
+				
+				if !palette_set_edits.is_dir() {
+					println!("Could not locate the directory of edited palettes for -palset-rebuild, aborting operation.");
+					return None;
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
+			ArgumentType::HEADER => {
+				match &this_argument.to_lowercase() as &str {
+					"core" => bmp_header = BmpHeader::CORE,
+					"info" => bmp_header = BmpHeader::INFO,
+					"v4" => bmp_header = BmpHeader::V4,
+					"v5" => bmp_header = BmpHeader::V5,
+					_ => {
+						println!("Unsupported BMP header '{}'. Supported headers: 'core', 'info', 'v4', 'v5'.", &args[argument]);
+						return None;
+					},
+				}
+				
+				next_arg = ArgumentType::NONE;
+				continue;
+			},
+			
 			_ => (),
 		}
 			
@@ -147,27 +350,78 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 			"-f" | "-format" => next_arg = ArgumentType::FORMAT,
 			"-w" | "-overwrite" => overwrite = true,
 			"-l" | "-list" => verbose = true,
+			"-rep" | "-report" => next_arg = ArgumentType::REPORT,
+			"-info" => info_mode = true,
+			"-al" | "-archive-list" => archive_mode = ArchiveMode::LIST,
+			"-ax" | "-archive-extract" => archive_mode = ArchiveMode::EXTRACT,
+			
+			"-ar" | "-archive-repack" => {
+				archive_mode = ArchiveMode::REPACK;
+				next_arg = ArgumentType::ARCHIVE;
+			},
+			
+			"-pse" | "-palset-export" => next_arg = ArgumentType::PALSET,
+			
+			"-psr" | "-palset-rebuild" => {
+				palette_set_mode = PaletteSetMode::REBUILD;
+				next_arg = ArgumentType::PALEDITS;
+			},
+			
+			"-ps16" | "-palset-16" => palette_set_small = true,
+			"-sw" | "-swatch" => next_arg = ArgumentType::SWATCH,
+			"-a" | "-animate" => next_arg = ArgumentType::ANIMATE,
+			"-d" | "-delay" => next_arg = ArgumentType::DELAY,
 			
 			// Palette parameters
 			"-p" | "-palette" => next_arg = ArgumentType::PALETTE,
 			"-c" | "-palcopy" => palette_transfer = true,
 			"-q" | "-opaque" => opaque = true,
+			"-pa" | "-ps2-alpha" => ps2_alpha = true,
+			"-cs" | "-clut-swizzle" => clut_swizzle = true,
 			
 			// Image procesing parameters
 			"-rgb" | "-as-rgb" => as_rgb = true,
 			
+			"-1" | "-force-1bpp" => {
+				forced_bit_depth = true;
+				compact_4bpp = false;
+				bit_depth = 1;
+			},
+			
+			"-2" | "-force-2bpp" => {
+				forced_bit_depth = true;
+				compact_4bpp = false;
+				bit_depth = 2;
+			},
+			
 			"-4" | "-force-4bpp" => {
 				forced_bit_depth = true;
+				compact_4bpp = false;
 				bit_depth = 4;
 			},
 			
 			"-8" | "-force-8bpp" => {
 				forced_bit_depth = true;
+				compact_4bpp = false;
 				bit_depth = 8;
 			},
 			
+			"-4c" | "-compact-4bpp" => {
+				forced_bit_depth = false;
+				compact_4bpp = true;
+				bit_depth = 4;
+			},
+			
 			"-r" | "-reindex" => reindex = true,
 			
+			"-rp" | "-reindex-pal" => {
+				reindex = true;
+				reindex_palette = true;
+			},
+			
+			"-m" | "-remap" => next_arg = ArgumentType::REMAP,
+			"-mp" | "-remap-pal" => remap_palette = true,
+			
 			// BIN sprite parameters
 			"-hs" | "-hash-set" => {
 				hash_mode = HashMode::PRESET;
@@ -179,13 +433,31 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 				next_arg = ArgumentType::HASH;
 			},
 			
+			"-hg" | "-hash-gen" => hash_mode = HashMode::REGENERATE,
+			"-tw" => next_arg = ArgumentType::TW,
+			"-th" => next_arg = ArgumentType::TH,
+			"-tf" | "-tex-fit" => fit_texture = true,
 			"-u" | "-uncompressed" => uncompressed = true,
 			
+			// TGA sprite parameters
+			"-rle" => rle = true,
+			
+			// BMP sprite parameters
+			"-bh" | "-bmp-header" => next_arg = ArgumentType::HEADER,
+			
 			// Invalid parameters
 			_ => println!("Unexpected parameter '{}', ignoring.", &this_argument),
 		}
 	}
 	
+	// Archives and palette sets can be named anything
+	let any_source: bool = archive_mode != ArchiveMode::NONE || palette_set_mode != PaletteSetMode::NONE;
+	
+	// RLE BMPs can't use BITMAPCOREHEADER
+	if rle && bmp_header == BmpHeader::CORE {
+		bmp_header = BmpHeader::INFO;
+	}
+	
 	// Insufficient parameters
 	// No source file
 	if source_file_name == "" {
@@ -209,6 +481,7 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 	
 	match source_pathbuf.extension() {
 		Some(os_str) => source_extension = os_str.to_str().unwrap(),
+		None if any_source => source_extension = "",
 		_ => {
 			println!("Source file format wasn't specified ('.png', '.raw', '.bin').");
 			return None;
@@ -222,20 +495,90 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 		"raw" => source_format = SpriteFormat::RAW,
 		"bin" => source_format = SpriteFormat::BIN,
 		"bmp" => source_format = SpriteFormat::BMP,
+		"gif" => source_format = SpriteFormat::GIF,
+		"tga" => source_format = SpriteFormat::TGA,
+		"pcx" => source_format = SpriteFormat::PCX,
+		
+		// Palettes, swatches only
+		"act" if swatch_mode != SwatchMode::NONE => source_format = SpriteFormat::ACT,
+		
+		// Archives and palette sets are read as they are
+		_ if any_source => source_format = SpriteFormat::NONE,
+		
 		_ => {
-			println!("Unsupported source format '{}'. Supported formats: 'png', 'raw', 'bin', 'bmp'.", source_extension);
+			println!("Unsupported source format '{}'. Supported formats: 'png', 'raw', 'bin', 'bmp', 'gif', 'tga', 'pcx'.", source_extension);
 			return None;
 		},
 	}
 	
+	// Animations decide the output format
+	if animate_format != SpriteFormat::NONE {
+		target_format = animate_format;
+	}
+	
 	if target_format == SpriteFormat::NONE {
 		target_format = source_format.clone();
 	}
 	
 	// Validate palette
 	let mut palette_pathbuf: PathBuf = PathBuf::from(source_palette);
+	let mut palette_batch: Vec<PathBuf> = Vec::new();
 	
-	if source_palette != "" {
+	// Multiple palettes, either a directory of ACT files or a comma-separated list
+	if source_palette != "" && (palette_pathbuf.is_dir() || source_palette.contains(',')) {
+		if palette_pathbuf.is_dir() {
+			match palette_pathbuf.read_dir() {
+				Ok(directory_items) => {
+					for item in directory_items {
+						match item {
+							Ok(entry) => {
+								let path: PathBuf = entry.path();
+								match path.extension() {
+									Some(os_str) if os_str.eq_ignore_ascii_case("act") => palette_batch.push(path),
+									_ => (),
+								}
+							},
+							
+							_ => (),
+						}
+					}
+				},
+				
+				_ => println!("param_validator::validate() error: Could not read palette directory, ignoring"),
+			}
+			
+			palette_batch.sort();
+		}
+		
+		else {
+			for palette in source_palette.split(',') {
+				let this_palette: PathBuf = PathBuf::from(palette.trim());
+				
+				match this_palette.try_exists() {
+					Ok(true) => palette_batch.push(this_palette),
+					_ => println!("Could not locate palette file '{}', ignoring.", palette.trim()),
+				}
+			}
+		}
+		
+		palette_pathbuf.clear();
+		
+		if palette_batch.is_empty() {
+			println!("No palettes were found, ignoring.");
+		}
+		
+		else if target_format == SpriteFormat::RAW {
+			println!("Palettes have been specified but output format is RAW, ignoring.");
+			palette_batch.clear();
+		}
+		
+		else if palette_transfer {
+			println!("Multiple palettes have been specified alongside -palcopy, ignoring palettes.");
+			palette_batch.clear();
+		}
+	}
+	
+	else if source_palette != "" {
 		match palette_pathbuf.try_exists() {
 			Ok(false) => {
 				println!("Could not locate specified palette file, ignoring.");
@@ -259,6 +602,21 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 		}
 	}
 	
+	// Load remap table
+	let mut remap: Vec<u8> = Vec::new();
+	
+	if source_remap != "" {
+		match remap_table::get_remap_table(&PathBuf::from(source_remap)) {
+			Some(table) => remap = table,
+			None => return None,
+		}
+	}
+	
+	else if remap_palette {
+		println!("-remap-pal was specified without a remap table, ignoring.");
+		remap_palette = false;
+	}
+	
 	// Return neatly packed list of arguments.
 	let final_source: PathBuf;
 	let final_directory_mode: bool;
@@ -303,23 +661,56 @@ pub fn validate(arg_count: usize, args: Vec<String>) -> Option<Parameters> {
 		}
 	}
 
+	if any_source && final_directory_mode {
+		println!("Archives and palette sets are processed one at a time (e.g. '-i chara.pac').");
+		return None;
+	}
+	
+	if animate_format != SpriteFormat::NONE && !final_directory_mode {
+		println!("Animations are made from a directory of frames (e.g. '-i frames/*.png').");
+		return None;
+	}
+	
 	// Final output
 	return Some(Parameters {
 		directory_mode: final_directory_mode,
 		source_path: final_source,
 		target_path: target_path,
 		palette_file: palette_pathbuf,
+		palette_batch: palette_batch,
 		source_format: source_format,
 		target_format: target_format,
+		target_suffix: String::new(),
 		palette_transfer: palette_transfer,
 		forced_bit_depth: forced_bit_depth,
 		bit_depth: bit_depth,
+		compact_4bpp: compact_4bpp,
 		as_rgb: as_rgb,
 		opaque: opaque,
+		ps2_alpha: ps2_alpha,
+		clut_swizzle: clut_swizzle,
 		hash_mode: hash_mode,
 		hash_value: hash_value,
+		tw_override: tw_override,
+		th_override: th_override,
+		fit_texture: fit_texture,
 		uncompressed: uncompressed,
+		rle: rle,
+		bmp_header: bmp_header,
 		reindex: reindex,
+		reindex_palette: reindex_palette,
+		remap_table: remap,
+		remap_palette: remap_palette,
+		report_mode: report_mode,
+		info_mode: info_mode,
+		archive_mode: archive_mode,
+		archive_sprites: archive_sprites,
+		palette_set_mode: palette_set_mode,
+		palette_set_edits: palette_set_edits,
+		palette_set_small: palette_set_small,
+		swatch_mode: swatch_mode,
+		animate_format: animate_format,
+		frame_delay: frame_delay,
 		verbose: verbose,
 		overwrite: overwrite,
 	});
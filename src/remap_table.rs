@@ -0,0 +1,168 @@
+use std::fs;
+
+use crate::PathBuf;
+
+const TABLE_SIZE: usize = 256;
+
+
+pub fn get_remap_table(source_file: &PathBuf) -> Option<Vec<u8>> {
+	let file_data: Vec<u8>;
+	match fs::read(source_file) {
+		Ok(value) => file_data = value,
+		_ => {
+			println!("remap_table::get_remap_table() error: Could not read remap file");
+			println!("\tFile: {}", source_file.display());
+			return None;
+		},
+	}
+	
+	// Text tables, either 'from:to' lines or 256 plain entries
+	if looks_like_text(&file_data) {
+		match parse_text_table(&String::from_utf8_lossy(&file_data)) {
+			Ok(table) => return Some(table),
+			
+			Err(message) => {
+				println!("Could not parse remap file: {}", message);
+				println!("\tFile: {}", source_file.display());
+				return None;
+			},
+		}
+	}
+	
+	// Binary table, one byte per index
+	if file_data.len() == TABLE_SIZE {
+		return Some(file_data);
+	}
+	
+	println!("Could not parse remap file: expected 256 entries or 'from:to' lines");
+	println!("\tFile: {}", source_file.display());
+	return None;
+}
+
+
+// Text tables only hold printable ASCII and line breaks, which a binary table can't
+// as it has to contain bytes below 0x20 or above 0x7E unless it maps everything
+// into that range
+fn looks_like_text(file_data: &[u8]) -> bool {
+	for byte in file_data {
+		match byte {
+			b'\t' | b'\n' | b'\r' | 0x20..=0x7E => (),
+			_ => return false,
+		}
+	}
+	
+	return true;
+}
+
+
+fn parse_index(value: &str) -> Result<u8, String> {
+	let value: &str = value.trim();
+	let parsed: Result<u8, std::num::ParseIntError>;
+	
+	if value.starts_with("0x") || value.starts_with("0X") {
+		parsed = u8::from_str_radix(&value[2..], 16);
+	}
+	else {
+		parsed = value.parse::<u8>();
+	}
+	
+	match parsed {
+		Ok(index) => return Ok(index),
+		_ => return Err(format!("'{}' is not an index between 0 and 255", value)),
+	}
+}
+
+
+fn parse_text_table(text: &str) -> Result<Vec<u8>, String> {
+	// Identity by default, only listed indices move
+	let mut table: Vec<u8> = (0..TABLE_SIZE).map(|index| index as u8).collect();
+	let mut plain_entries: Vec<u8> = Vec::new();
+	let mut pair_count: usize = 0;
+	
+	for line in text.lines() {
+		// Strip comments
+		let line: &str = line.split('#').next().unwrap().trim();
+		
+		if line.is_empty() {
+			continue;
+		}
+		
+		if line.contains(':') {
+			let pieces: Vec<&str> = line.split(':').collect();
+			
+			if pieces.len() != 2 {
+				return Err(format!("malformed line '{}'", line));
+			}
+			
+			let from: u8 = parse_index(pieces[0])?;
+			let to: u8 = parse_index(pieces[1])?;
+			table[from as usize] = to;
+			pair_count += 1;
+		}
+		
+		else {
+			for entry in line.split(|c: char| c == ',' || c.is_whitespace()) {
+				if !entry.is_empty() {
+					plain_entries.push(parse_index(entry)?);
+				}
+			}
+		}
+	}
+	
+	if pair_count > 0 && !plain_entries.is_empty() {
+		return Err("cannot mix 'from:to' lines with plain entries".to_string());
+	}
+	
+	if !plain_entries.is_empty() {
+		if plain_entries.len() != TABLE_SIZE {
+			return Err(format!("found {} plain entries, expected 256", plain_entries.len()));
+		}
+		
+		return Ok(plain_entries);
+	}
+	
+	if pair_count == 0 {
+		return Err("no entries found".to_string());
+	}
+	
+	return Ok(table);
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn binary_identity_table_is_not_text() {
+		let table: Vec<u8> = (0..TABLE_SIZE).map(|index| index as u8).collect();
+		assert!(!looks_like_text(&table));
+	}
+	
+	#[test]
+	fn text_tables_are_text() {
+		assert!(looks_like_text(b"# swap two colors\r\n1:2\n2:1\n"));
+		assert!(looks_like_text(b"0x10,\t0x11"));
+	}
+	
+	#[test]
+	fn pair_lines_remap_listed_indices() {
+		let table: Vec<u8> = parse_text_table("1:2\n0x02 : 0x01 # comment\n").unwrap();
+		
+		assert_eq!(table[0], 0);
+		assert_eq!(table[1], 2);
+		assert_eq!(table[2], 1);
+		assert_eq!(table[255], 255);
+	}
+	
+	#[test]
+	fn broken_text_table_is_an_error() {
+		// 256 bytes of text used to fall back to a binary table
+		let mut text: String = "1:300\n".to_string();
+		text.push_str(&" ".repeat(TABLE_SIZE - text.len()));
+		
+		assert_eq!(text.len(), TABLE_SIZE);
+		assert!(looks_like_text(text.as_bytes()));
+		assert!(parse_text_table(&text).is_err());
+	}
+}
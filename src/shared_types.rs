@@ -1,4 +1,5 @@
 use crate::PathBuf;
+use crate::bin_header::TextureExponent;
 
 #[derive(Clone)]
 pub struct Parameters {
@@ -6,17 +7,40 @@ pub struct Parameters {
 	pub source_path: PathBuf,
 	pub target_path: PathBuf,
 	pub palette_file: PathBuf,
+	pub palette_batch: Vec<PathBuf>,
 	pub source_format: SpriteFormat,
 	pub target_format: SpriteFormat,
+	pub target_suffix: String,
 	pub palette_transfer: bool,
 	pub forced_bit_depth: bool,
 	pub bit_depth: usize,
+	pub compact_4bpp: bool,
 	pub as_rgb: bool,
 	pub opaque: bool,
+	pub ps2_alpha: bool,
+	pub clut_swizzle: bool,
 	pub uncompressed: bool,
+	pub bmp_header: BmpHeader,
+	pub rle: bool,
 	pub reindex: bool,
+	pub reindex_palette: bool,
+	pub remap_table: Vec<u8>,
+	pub remap_palette: bool,
 	pub hash_mode: HashMode,
 	pub hash_value: u16,
+	pub tw_override: Option<TextureExponent>,
+	pub th_override: Option<TextureExponent>,
+	pub fit_texture: bool,
+	pub report_mode: ReportMode,
+	pub info_mode: bool,
+	pub archive_mode: ArchiveMode,
+	pub archive_sprites: PathBuf,
+	pub palette_set_mode: PaletteSetMode,
+	pub palette_set_edits: PathBuf,
+	pub palette_set_small: bool,
+	pub swatch_mode: SwatchMode,
+	pub animate_format: SpriteFormat,
+	pub frame_delay: u32,
 	pub verbose: bool,
 	pub overwrite: bool,
 }
@@ -28,11 +52,55 @@ pub enum SpriteFormat {
 	RAW,
 	BIN,
 	BMP,
+	GIF,
+	TGA,
+	PCX,
+	ACT,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum BmpHeader {
+	CORE,
+	INFO,
+	V4,
+	V5,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum SwatchMode {
+	NONE,
+	GRID,
+	LABELS,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ReportMode {
+	NONE,
+	TEXT,
+	CSV,
+	JSON,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ArchiveMode {
+	NONE,
+	LIST,
+	EXTRACT,
+	REPACK,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum PaletteSetMode {
+	NONE,
+	ACT,
+	GPL,
+	REBUILD,
 }
 
 #[derive(PartialEq, Clone)]
 pub enum HashMode {
 	GENERATE,
+	REGENERATE,
 	PRESET,
 	INCREMENTAL,
 }
@@ -44,6 +112,23 @@ pub struct SpriteData {
 	pub bit_depth: u16,
 	pub pixels: Vec<u8>,
 	pub palette: Vec<u8>,
+	pub metadata: SpriteMetadata,
+}
+
+// Provenance of a sprite that came from a BIN, kept so it can be written back
+#[derive(Clone, Default)]
+pub struct SpriteMetadata {
+	pub source_path: String,
+	pub hash: Option<u16>,
+	pub tw: Option<TextureExponent>,
+	pub th: Option<TextureExponent>,
+	pub compressed: Option<bool>,
+	pub version: String,
+	// Palette alpha is the default +R alpha filled in by the reader rather than read
+	// from the file, so it's PS2 alpha whatever the source format
+	pub alpha_filled: bool,
+	// Unknown BIN clut value and the color table that came with it, kept as they are
+	pub raw_clut: Option<(u16, Vec<u8>)>,
 }
 
 impl Default for SpriteData {
@@ -54,6 +139,7 @@ impl Default for SpriteData {
 			bit_depth: 8,
 			pixels: Vec::new(),
 			palette: Vec::new(),
+			metadata: SpriteMetadata::default(),
 		}
 	}
 }
@@ -0,0 +1,69 @@
+use crate::{
+	PathBuf,
+	Parameters,
+	SpriteData,
+	SpriteFormat,
+	get_directory_files,
+	type_matches,
+	load_sprite,
+	apply_palette,
+	sprite_make,
+};
+
+
+pub fn animate(parameters: Parameters) -> usize {
+	let mut frames: Vec<SpriteData> = Vec::new();
+	
+	// Frames are ordered by file name
+	for file in get_directory_files(&parameters.source_path) {
+		if !type_matches(file.extension(), parameters.source_format) {
+			continue;
+		}
+		
+		let mut frame_parameters: Parameters = parameters.clone();
+		frame_parameters.source_path = file.clone();
+		
+		if parameters.verbose {
+			println!("Processing '{}'", file.display());
+		}
+		
+		let data: SpriteData = load_sprite(&frame_parameters);
+		
+		if data.width == 0 || data.height == 0 {
+			continue;
+		}
+		
+		frames.push(apply_palette(&frame_parameters, data));
+	}
+	
+	if frames.is_empty() {
+		println!("No frames to animate.");
+		return 0;
+	}
+	
+	// Name the animation after its directory
+	let mut animation_parameters: Parameters = parameters.clone();
+	let directory_name: String;
+	
+	match parameters.source_path.canonicalize() {
+		Ok(path) => match path.file_name() {
+			Some(name) => directory_name = name.to_string_lossy().to_string(),
+			None => directory_name = String::from("animation"),
+		},
+		
+		_ => directory_name = String::from("animation"),
+	}
+	
+	// Extension is replaced when writing
+	animation_parameters.source_path = PathBuf::from(format!("{}.frames", directory_name));
+	
+	let frame_count: usize = frames.len();
+	
+	match parameters.animate_format {
+		SpriteFormat::GIF => sprite_make::make_gif_animation(animation_parameters, frames),
+		SpriteFormat::PNG => sprite_make::make_png_animation(animation_parameters, frames),
+		_ => println!("sprite_animate::animate() error: Invalid animation format provided"),
+	}
+	
+	return frame_count;
+}
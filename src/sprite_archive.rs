@@ -0,0 +1,435 @@
+use std::fs;
+use std::fs::File;
+use std::ffi::OsString;
+use std::io::{BufWriter, Write};
+
+use crate::{
+	PathBuf,
+	Parameters,
+	shared_types::ArchiveMode,
+	bin_header,
+	bin_header::BinHeader,
+	sprite_compress,
+	sprite_make,
+};
+
+// The layout of the game's archives (their offset tables and whatever sits around
+// the sprites) isn't known, so sprites are found by looking for BIN headers on
+// 16-byte boundaries and checking that what follows decodes to exactly the sprite
+// the header describes. Repacking writes a sprite back into its own slot, which keeps
+// every offset in the archive valid without having to understand them. A sprite that
+// outgrows its slot pushes everything after it back, and since the offset tables
+// can't be rewritten, the game may then look for that data in the wrong place.
+
+struct ArchiveEntry {
+	offset: usize,
+	// Sprite bytes
+	length: usize,
+	// Sprite bytes plus their 0xFF padding
+	slot_length: usize,
+	header: BinHeader,
+}
+
+
+pub fn archive(parameters: Parameters) -> usize {
+	let archive_data: Vec<u8>;
+	match fs::read(&parameters.source_path) {
+		Ok(value) => archive_data = value,
+		_ => {
+			println!("sprite_archive::archive() error: Archive file read error");
+			println!("\tSkipped: {}", parameters.source_path.display());
+			return 0;
+		},
+	}
+	
+	let entries: Vec<ArchiveEntry> = find_sprites(&archive_data);
+	
+	if entries.is_empty() {
+		println!("Warning: No sprites were found in archive");
+		println!("\tFile: {}", parameters.source_path.display());
+		return 0;
+	}
+	
+	match parameters.archive_mode {
+		ArchiveMode::LIST => return list(&parameters, &entries),
+		ArchiveMode::EXTRACT => return extract(&parameters, &archive_data, &entries),
+		ArchiveMode::REPACK => return repack(&parameters, archive_data, &entries),
+		ArchiveMode::NONE => return 0,
+	}
+}
+
+
+// Sprites extracted from an archive are named after it and numbered in file order
+fn entry_name(parameters: &Parameters, index: usize) -> OsString {
+	return sprite_make::stem_with_suffix(&parameters.source_path, &format!("_{:03}.bin", index));
+}
+
+
+fn find_sprites(archive_data: &Vec<u8>) -> Vec<ArchiveEntry> {
+	let mut entries: Vec<ArchiveEntry> = Vec::new();
+	let mut offset: usize = 0;
+	
+	while offset + bin_header::HEADER_SIZE <= archive_data.len() {
+		match sprite_length(archive_data, offset) {
+			Some(length) => {
+				let padded_length: usize = (length + bin_header::FILE_ALIGNMENT - 1) / bin_header::FILE_ALIGNMENT * bin_header::FILE_ALIGNMENT;
+				let slot_length: usize = std::cmp::min(padded_length, archive_data.len() - offset);
+				
+				entries.push(ArchiveEntry {
+					offset: offset,
+					length: length,
+					slot_length: slot_length,
+					header: bin_header::get_header(archive_data[offset..offset + bin_header::HEADER_SIZE].to_vec()),
+				});
+				
+				offset += padded_length;
+			},
+			
+			None => offset += bin_header::FILE_ALIGNMENT,
+		}
+	}
+	
+	return entries;
+}
+
+
+// Length of the BIN sprite at offset, or None if there isn't a valid one there
+fn sprite_length(data: &Vec<u8>, offset: usize) -> Option<usize> {
+	if offset + bin_header::HEADER_SIZE > data.len() {
+		return None;
+	}
+	
+	// Compression mode is a single 0 or 1 byte
+	if data[offset] > 1 || data[offset + 1] != 0 {
+		return None;
+	}
+	
+	let header: BinHeader = bin_header::get_header(data[offset..offset + bin_header::HEADER_SIZE].to_vec());
+	
+	match header.bit_depth {
+		1 | 2 | 4 | 8 => (),
+		_ => return None,
+	}
+	
+	bin_header::clut_entry_size(header.clut)?;
+	
+	if header.width == 0 || header.height == 0 {
+		return None;
+	}
+	
+	if !bin_header::exponent_valid(header.tw) || !bin_header::exponent_valid(header.th) {
+		return None;
+	}
+	
+	let pointer: usize = offset + bin_header::HEADER_SIZE + bin_header::palette_length(&header);
+	
+	if pointer > data.len() {
+		return None;
+	}
+	
+	let body_length: usize;
+	
+	if header.compressed {
+		body_length = sprite_compress::stream_length(data, pointer, &header)?;
+	}
+	
+	else {
+		body_length = bin_header::pixel_byte_count(&header);
+		
+		if pointer + body_length > data.len() {
+			return None;
+		}
+	}
+	
+	return Some(pointer + body_length - offset);
+}
+
+
+fn list(parameters: &Parameters, entries: &Vec<ArchiveEntry>) -> usize {
+	println!("{}", parameters.source_path.display());
+	
+	for index in 0..entries.len() {
+		let entry: &ArchiveEntry = &entries[index];
+		
+		let compression: &str;
+		if entry.header.compressed {
+			compression = "compressed";
+		}
+		else {
+			compression = "uncompressed";
+		}
+		
+		println!(
+			"    {}  offset 0x{:08X}, {} bytes, {}x{}, {} bpp, {}",
+			entry_name(parameters, index).to_string_lossy(), entry.offset, entry.length,
+			entry.header.width, entry.header.height, entry.header.bit_depth, compression
+		);
+	}
+	
+	return entries.len();
+}
+
+
+fn extract(parameters: &Parameters, archive_data: &Vec<u8>, entries: &Vec<ArchiveEntry>) -> usize {
+	let mut extracted_count: usize = 0;
+	
+	for index in 0..entries.len() {
+		let entry: &ArchiveEntry = &entries[index];
+		let mut target_path: PathBuf = parameters.target_path.clone();
+		target_path.push(entry_name(parameters, index));
+		
+		if sprite_make::overwrite_blocked(&target_path, parameters.overwrite) {
+			continue;
+		}
+		
+		let bin_file: File;
+		match File::create(&target_path) {
+			Ok(file) => bin_file = file,
+			_ => {
+				println!("sprite_archive::extract() error: Could not create target BIN file");
+				println!("\tSkipped: {}", &target_path.display());
+				continue;
+			},
+		}
+		
+		// Padding included, so an untouched sprite repacks byte for byte
+		let ref mut buffer = BufWriter::new(bin_file);
+		let _ = buffer.write_all(&archive_data[entry.offset..entry.offset + entry.slot_length]);
+		let _ = buffer.flush();
+		
+		if parameters.verbose {
+			println!("Extracted '{}'", target_path.display());
+		}
+		
+		extracted_count += 1;
+	}
+	
+	return extracted_count;
+}
+
+
+// Write into the slot, shift bytes after entries that grew before it, and pad the
+// rest of it. Whatever follows the sprite in its file (such as a stray last stream
+// byte) is kept as long as it fits. Slots too small for sprite_length bytes grow to
+// the next 16-byte boundary, the return value is by how much.
+fn write_slot(archive_data: &mut Vec<u8>, entry: &ArchiveEntry, shift: usize, sprite_data: &Vec<u8>, sprite_length: usize) -> usize {
+	let offset: usize = entry.offset + shift;
+	let mut slot_length: usize = entry.slot_length;
+	let mut growth: usize = 0;
+	
+	if sprite_length > slot_length {
+		let padded_length: usize = (sprite_length + bin_header::FILE_ALIGNMENT - 1) / bin_header::FILE_ALIGNMENT * bin_header::FILE_ALIGNMENT;
+		growth = padded_length - slot_length;
+		archive_data.splice(offset + slot_length..offset + slot_length, std::iter::repeat_n(0xFF, growth));
+		slot_length = padded_length;
+	}
+	
+	let copy_length: usize = std::cmp::min(sprite_data.len(), slot_length);
+	let slot: &mut [u8] = &mut archive_data[offset..offset + slot_length];
+	slot[..copy_length].copy_from_slice(&sprite_data[..copy_length]);
+	slot[copy_length..].fill(0xFF);
+	
+	return growth;
+}
+
+
+fn repack(parameters: &Parameters, mut archive_data: Vec<u8>, entries: &Vec<ArchiveEntry>) -> usize {
+	let mut target_path: PathBuf = parameters.target_path.clone();
+	target_path.push(parameters.source_path.file_name().unwrap());
+	
+	if sprite_make::overwrite_blocked(&target_path, parameters.overwrite) {
+		return 0;
+	}
+	
+	let mut replaced_count: usize = 0;
+	
+	// Bytes added by slots that grew so far, everything after them moved back by this much
+	let mut shift: usize = 0;
+	
+	for index in 0..entries.len() {
+		let entry: &ArchiveEntry = &entries[index];
+		let mut sprite_path: PathBuf = parameters.archive_sprites.clone();
+		sprite_path.push(entry_name(parameters, index));
+		
+		// Sprites without a replacement are kept as they are
+		let sprite_data: Vec<u8>;
+		match fs::read(&sprite_path) {
+			Ok(value) => sprite_data = value,
+			_ => continue,
+		}
+		
+		let replacement_length: usize;
+		match sprite_length(&sprite_data, 0) {
+			Some(length) => replacement_length = length,
+			None => {
+				println!("sprite_archive::repack() error: Replacement is not a valid BIN sprite, keeping the original");
+				println!("\tSkipped: {}", sprite_path.display());
+				continue;
+			},
+		}
+		
+		let growth: usize = write_slot(&mut archive_data, entry, shift, &sprite_data, replacement_length);
+		
+		if growth > 0 {
+			println!(
+				"Warning: Replacement is {} bytes but its slot only held {}, everything after it moved back {} bytes",
+				replacement_length, entry.slot_length, growth
+			);
+			println!("\tThe archive's offset tables aren't rewritten, so the game may not find the data after it");
+			println!("\tFile: {}", sprite_path.display());
+		}
+		
+		shift += growth;
+		
+		if parameters.verbose {
+			println!("Replaced {} with '{}'", entry_name(parameters, index).to_string_lossy(), sprite_path.display());
+		}
+		
+		replaced_count += 1;
+	}
+	
+	let archive_file: File;
+	match File::create(&target_path) {
+		Ok(file) => archive_file = file,
+		_ => {
+			println!("sprite_archive::repack() error: Could not create target archive file");
+			println!("\tSkipped: {}", &target_path.display());
+			return 0;
+		},
+	}
+	
+	let ref mut buffer = BufWriter::new(archive_file);
+	let _ = buffer.write_all(&archive_data);
+	let _ = buffer.flush();
+	
+	return replaced_count;
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	// Uncompressed 4 bpp BIN with an embedded palette, padded like an archive slot
+	fn padded_bin(width: u16, height: u16, fill: u8) -> Vec<u8> {
+		let mut bin_data: Vec<u8> = bin_header::get_bytes(BinHeader {
+			compressed: false,
+			clut: bin_header::CLUT_32,
+			bit_depth: 4,
+			width: width,
+			height: height,
+			tw: bin_header::TextureExponent(0),
+			th: bin_header::TextureExponent(0),
+			hash: 0,
+		});
+		
+		for color in 0..16 {
+			bin_data.extend_from_slice(&[color * 16, color * 16, color * 16, 0x80]);
+		}
+		
+		bin_data.resize(bin_data.len() + (width as usize * height as usize + 1) / 2, fill);
+		
+		while bin_data.len() % bin_header::FILE_ALIGNMENT != 0 {
+			bin_data.push(0xFF);
+		}
+		
+		return bin_data;
+	}
+	
+	// Two sprites between blocks that aren't sprites
+	fn synthetic_archive() -> Vec<u8> {
+		let mut archive_data: Vec<u8> = vec![0xAA; 32];
+		archive_data.extend_from_slice(&padded_bin(8, 4, 0x11));
+		archive_data.extend_from_slice(&[0x55; 16]);
+		archive_data.extend_from_slice(&padded_bin(3, 3, 0x22));
+		archive_data.extend_from_slice(&[0xAA; 16]);
+		return archive_data;
+	}
+	
+	#[test]
+	fn finds_sprites_between_other_data() {
+		let entries: Vec<ArchiveEntry> = find_sprites(&synthetic_archive());
+		
+		assert_eq!(entries.len(), 2);
+		assert_eq!((entries[0].offset, entries[0].length, entries[0].slot_length), (32, 96, 96));
+		assert_eq!((entries[1].offset, entries[1].length, entries[1].slot_length), (144, 85, 96));
+		assert_eq!((entries[1].header.width, entries[1].header.height), (3, 3));
+	}
+	
+	#[test]
+	fn finds_sprites_wider_than_1024() {
+		let entries: Vec<ArchiveEntry> = find_sprites(&padded_bin(2048, 1, 0x33));
+		
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].header.width, 2048);
+	}
+	
+	#[test]
+	fn repack_replaces_only_its_slot() {
+		let archive_data: Vec<u8> = synthetic_archive();
+		let entries: Vec<ArchiveEntry> = find_sprites(&archive_data);
+		
+		// Same size, different pixels
+		let mut repacked: Vec<u8> = archive_data.clone();
+		let replacement: Vec<u8> = padded_bin(3, 3, 0x44);
+		assert_eq!(sprite_length(&replacement, 0), Some(entries[1].length));
+		assert_eq!(write_slot(&mut repacked, &entries[1], 0, &replacement, entries[1].length), 0);
+		
+		assert_eq!(repacked.len(), archive_data.len());
+		assert_eq!(&repacked[..144], &archive_data[..144]);
+		assert_eq!(&repacked[144..240], &replacement[..]);
+		assert_eq!(&repacked[240..], &archive_data[240..]);
+		
+		let repacked_entries: Vec<ArchiveEntry> = find_sprites(&repacked);
+		assert_eq!(repacked_entries.len(), 2);
+		assert_eq!(repacked_entries[1].offset, entries[1].offset);
+		
+		// Untouched sprites go back in byte for byte
+		let mut unchanged: Vec<u8> = archive_data.clone();
+		let extracted: Vec<u8> = archive_data[entries[0].offset..entries[0].offset + entries[0].slot_length].to_vec();
+		write_slot(&mut unchanged, &entries[0], 0, &extracted, entries[0].length);
+		assert_eq!(unchanged, archive_data);
+	}
+	
+	#[test]
+	fn smaller_replacement_is_padded() {
+		let archive_data: Vec<u8> = synthetic_archive();
+		let entries: Vec<ArchiveEntry> = find_sprites(&archive_data);
+		
+		let mut repacked: Vec<u8> = archive_data.clone();
+		let replacement: Vec<u8> = padded_bin(2, 2, 0x44);
+		write_slot(&mut repacked, &entries[0], 0, &replacement, sprite_length(&replacement, 0).unwrap());
+		
+		assert_eq!(&repacked[32..32 + replacement.len()], &replacement[..]);
+		assert!(repacked[32 + replacement.len()..128].iter().all(|byte| *byte == 0xFF));
+		assert_eq!(&repacked[128..], &archive_data[128..]);
+	}
+	
+	#[test]
+	fn larger_replacement_grows_its_slot() {
+		let archive_data: Vec<u8> = synthetic_archive();
+		let entries: Vec<ArchiveEntry> = find_sprites(&archive_data);
+		
+		// 16x16 is 208 bytes, 112 more than the 96-byte slot
+		let mut repacked: Vec<u8> = archive_data.clone();
+		let replacement: Vec<u8> = padded_bin(16, 16, 0x44);
+		let growth: usize = write_slot(&mut repacked, &entries[0], 0, &replacement, sprite_length(&replacement, 0).unwrap());
+		
+		assert_eq!(growth, 112);
+		assert_eq!(repacked.len(), archive_data.len() + 112);
+		assert_eq!(&repacked[..32], &archive_data[..32]);
+		assert_eq!(&repacked[32..240], &replacement[..]);
+		assert_eq!(&repacked[240..], &archive_data[128..]);
+		
+		// The second sprite moved back with everything else and can still be replaced
+		let second: Vec<u8> = padded_bin(3, 3, 0x66);
+		write_slot(&mut repacked, &entries[1], growth, &second, entries[1].length);
+		
+		let repacked_entries: Vec<ArchiveEntry> = find_sprites(&repacked);
+		assert_eq!(repacked_entries.len(), 2);
+		assert_eq!(repacked_entries[0].length, 208);
+		assert_eq!(repacked_entries[1].offset, entries[1].offset + 112);
+		assert_eq!(&repacked[256..352], &second[..]);
+		assert_eq!(&repacked[352..], &archive_data[240..]);
+	}
+}
@@ -3,7 +3,8 @@ use std::cmp::min;
 use bitstream_io::{BitReader, BitRead, BitWriter, BitWrite, BigEndian};
 
 use crate::{
-	shared_types::{SpriteData, CompressedData},
+	shared_types::{SpriteData, SpriteMetadata, CompressedData},
+	bin_header,
 	bin_header::BinHeader,
 	sprite_transform,
 };
@@ -15,9 +16,8 @@ const TOKEN_SIZE_MAX: usize = 130;
 pub fn compress(mut data: SpriteData) -> CompressedData {
 	// Bit depth management
 	match data.bit_depth {
-		// 1 and 2 bpp not currently in use
-		// 1 => data.pixels = sprite_transform::bpp_to_1(data.pixels, true),
-		// 2 => data.pixels = sprite_transform::bpp_to_2(data.pixels, true),
+		1 => data.pixels = sprite_transform::bpp_to_1(data.pixels, true),
+		2 => data.pixels = sprite_transform::bpp_to_2(data.pixels, true),
 		4 => data.pixels = sprite_transform::bpp_to_4(data.pixels, true),
 		8 => (), // No transform needed
 		// Shouldn't ever happen
@@ -110,8 +110,8 @@ pub fn compress(mut data: SpriteData) -> CompressedData {
 	
 	let file_byte_length: usize = compressed_stream.len() + 20;
 	
-	if file_byte_length % 16 != 0 {
-		for _i in 0..(16 - file_byte_length % 16) {
+	if file_byte_length % bin_header::FILE_ALIGNMENT != 0 {
+		for _i in 0..(bin_header::FILE_ALIGNMENT - file_byte_length % bin_header::FILE_ALIGNMENT) {
 			compressed_stream.push(255);
 		}
 	}
@@ -123,25 +123,16 @@ pub fn compress(mut data: SpriteData) -> CompressedData {
 }
 
 
-pub fn decompress(bin_data: Vec<u8>, header: BinHeader) -> SpriteData {
-	let pixel_count: usize = header.width as usize * header.height as usize;
+// table_length covers the embedded palette, or the table of an unknown clut value
+pub fn decompress(bin_data: Vec<u8>, header: BinHeader, table_length: usize) -> SpriteData {
+	let byte_count: usize = bin_header::pixel_byte_count(&header);
 	let mut pointer: usize = 0x10;
 	let mut palette: Vec<u8> = Vec::new();
 	
 	// Get embedded palette
-	if header.clut == 0x20 {
-		let color_count: usize = 2u16.pow(header.bit_depth as u32) as usize;
-		
-		// Get palette
-		for index in 0..color_count {
-			// RGBA
-			palette.push(bin_data[pointer + 4 * index + 0]);
-			palette.push(bin_data[pointer + 4 * index + 1]);
-			palette.push(bin_data[pointer + 4 * index + 2]);
-			palette.push(bin_data[pointer + 4 * index + 3]);
-		}
-		
-		pointer += color_count * 4;
+	if table_length > 0 {
+		palette = bin_header::get_palette(&bin_data[pointer..pointer + table_length], header.clut);
+		pointer += table_length;
 	}
 	
 	// Read iterations
@@ -174,8 +165,10 @@ pub fn decompress(bin_data: Vec<u8>, header: BinHeader) -> SpriteData {
 		if bit_reader.read_bit().unwrap() == true {
 			pixel_vector.push(bit_reader.read(8).unwrap());
 			
-			// Stray byte guard rail
-			if pixel_vector.len() + 1 < pixel_count {
+			// Stray byte guard rail. Literals always carry two bytes, the second
+			// one is only data if the sprite still needs it. (1.0.0 stopped one byte
+			// early, losing the last pixel of 8 bpp sprites that end on a literal.)
+			if pixel_vector.len() < byte_count {
 				pixel_vector.push(bit_reader.read(8).unwrap());
 			}
 		}
@@ -198,9 +191,8 @@ pub fn decompress(bin_data: Vec<u8>, header: BinHeader) -> SpriteData {
 	
 	// Bit depth management
 	match header.bit_depth {
-		// 1 and 2 bpp not currently used
-		// 1 => pixel_vector = sprite_transform::bpp_from_1(pixel_vector, true),
-		// 2 => pixel_vector = sprite_transform::bpp_from_2(pixel_vector, true),
+		1 => pixel_vector = sprite_transform::bpp_from_1(pixel_vector, true),
+		2 => pixel_vector = sprite_transform::bpp_from_2(pixel_vector, true),
 		4 => pixel_vector = sprite_transform::bpp_from_4(pixel_vector, true),
 		8 => (), // No transform needed
 		// Shouldn't ever happen
@@ -215,5 +207,165 @@ pub fn decompress(bin_data: Vec<u8>, header: BinHeader) -> SpriteData {
 		bit_depth: header.bit_depth,
 		pixels: pixel_vector,
 		palette: palette,
+		metadata: SpriteMetadata::default(),
 	};
-}
\ No newline at end of file
+}
+
+// Walks a compressed stream without decoding it and returns how many bytes it
+// takes up, iterations included, or None if it doesn't decode to exactly the
+// sprite's pixel data. Used to find where a sprite ends inside an archive.
+pub fn stream_length(bin_data: &[u8], pointer: usize, header: &BinHeader) -> Option<usize> {
+	let byte_count: usize = bin_header::pixel_byte_count(header);
+	
+	if pointer + 4 > bin_data.len() {
+		return None;
+	}
+	
+	let iterations: usize = u32::from_le_bytes([
+		bin_data[pointer + 0x02],
+		bin_data[pointer + 0x03],
+		bin_data[pointer + 0x00],
+		bin_data[pointer + 0x01]
+	]) as usize;
+	
+	// Every iteration makes at least one byte
+	if iterations == 0 || iterations > byte_count {
+		return None;
+	}
+	
+	let stream: &[u8] = &bin_data[pointer + 0x04..];
+	let mut bit_position: usize = 0;
+	let mut produced: usize = 0;
+	
+	for _i in 0..iterations {
+		// Literal mode
+		if read_bits(stream, &mut bit_position, 1)? == 1 {
+			read_bits(stream, &mut bit_position, 8)?;
+			produced += 1;
+			
+			if produced < byte_count {
+				read_bits(stream, &mut bit_position, 8)?;
+				produced += 1;
+			}
+		}
+		
+		// Token mode
+		else {
+			let window_origin: usize = produced.saturating_sub(WINDOW_SIZE);
+			let offset: usize = read_bits(stream, &mut bit_position, 9)?;
+			let length: usize = 3 + read_bits(stream, &mut bit_position, 7)?;
+			
+			if window_origin + offset >= produced {
+				return None;
+			}
+			
+			produced += length;
+		}
+		
+		if produced > byte_count {
+			return None;
+		}
+	}
+	
+	if produced != byte_count {
+		return None;
+	}
+	
+	// Round up to whole 16-bit words
+	let stream_bytes: usize = (bit_position + 15) / 16 * 2;
+	
+	return Some(0x04 + stream_bytes);
+}
+
+
+// The stream is stored as LE 16-bit words, so logical byte n is physical byte n ^ 1
+fn read_bits(stream: &[u8], bit_position: &mut usize, bit_count: usize) -> Option<usize> {
+	let mut value: usize = 0;
+	
+	for _i in 0..bit_count {
+		let byte: u8 = *stream.get((*bit_position / 8) ^ 1)?;
+		let bit: usize = ((byte >> (7 - *bit_position % 8)) & 0x1) as usize;
+		value = (value << 1) | bit;
+		*bit_position += 1;
+	}
+	
+	return Some(value);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::bin_header;
+	
+	// 8 bpp BINs written by ghoul 1.0.0 from a 6x2 sprite of pixels 1 to 12 (only
+	// literals) and an 8x2 sprite of 1, 2, 3, 4 repeated (ends on a token)
+	const LITERAL_BIN: [u8; 48] = [
+		0x01, 0x00, 0x00, 0x00, 0x08, 0x00, 0x06, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1E, 0xE1,
+		0x00, 0x00, 0x06, 0x00, 0x81, 0x80, 0xC1, 0x40, 0xA0, 0x20, 0x70, 0xD0, 0x48, 0x88, 0x2C, 0x54,
+		0xFF, 0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+	];
+	
+	const TOKEN_BIN: [u8; 32] = [
+		0x01, 0x00, 0x00, 0x00, 0x08, 0x00, 0x08, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x84, 0x84,
+		0x00, 0x00, 0x04, 0x00, 0x81, 0x80, 0xC1, 0x40, 0x00, 0x00, 0x00, 0x20, 0xFF, 0x50, 0xFF, 0xFF,
+	];
+	
+	fn decompress_bin(bin_data: &[u8]) -> SpriteData {
+		let header: BinHeader = bin_header::get_header(bin_data.to_vec());
+		return decompress(bin_data.to_vec(), header, 0);
+	}
+	
+	#[test]
+	fn decompress_8bpp_ending_on_token() {
+		// Same output as 1.0.0
+		let data: SpriteData = decompress_bin(&TOKEN_BIN);
+		
+		assert_eq!(data.pixels, [1, 2, 3, 4].repeat(4));
+	}
+	
+	#[test]
+	fn decompress_8bpp_ending_on_literal() {
+		// 1.0.0 read the last pixel as 0 here
+		let data: SpriteData = decompress_bin(&LITERAL_BIN);
+		
+		assert_eq!(data.pixels, (1..=12).collect::<Vec<u8>>());
+	}
+	
+	#[test]
+	fn decompress_8bpp_odd_pixel_count() {
+		// The stray second byte of the last literal isn't a pixel
+		let data: SpriteData = SpriteData {
+			width: 5,
+			height: 1,
+			bit_depth: 8,
+			pixels: vec![9, 8, 7, 6, 5],
+			palette: Vec::new(),
+			metadata: SpriteMetadata::default(),
+		};
+		
+		let compressed_data: CompressedData = compress(data);
+		let iterations: u32 = compressed_data.iterations as u32;
+		let mut bin_data: Vec<u8> = vec![0; bin_header::HEADER_SIZE];
+		bin_data.extend_from_slice(&iterations.to_le_bytes()[2..4]);
+		bin_data.extend_from_slice(&iterations.to_le_bytes()[0..2]);
+		
+		// Stream as LE 16-bit words, like make_bin() writes it
+		for word in 0..compressed_data.stream.len() / 2 {
+			bin_data.push(compressed_data.stream[2 * word + 1]);
+			bin_data.push(compressed_data.stream[2 * word]);
+		}
+		
+		let header: BinHeader = bin_header::BinHeader {
+			compressed: true,
+			clut: bin_header::CLUT_NONE,
+			bit_depth: 8,
+			width: 5,
+			height: 1,
+			tw: bin_header::TextureExponent(0),
+			th: bin_header::TextureExponent(0),
+			hash: 0,
+		};
+		
+		assert_eq!(decompress(bin_data, header, 0).pixels, vec![9, 8, 7, 6, 5]);
+	}
+}
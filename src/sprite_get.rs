@@ -5,8 +5,10 @@ use bmp_rust::bmp::{BMP, BITMAPFILEHEADER, DIBHEADER};
 use crate::{
 	PathBuf,
 	SpriteData,
+	SpriteFormat,
+	shared_types::SpriteMetadata,
 	bin_header,
-	bin_header::BinHeader,
+	bin_header::{BinHeader, TextureExponent},
 	sprite_compress,
 	sprite_transform,
 };
@@ -14,6 +16,31 @@ use crate::{
 const BITMAPCOREHEADER_SIZE: usize = 12;
 const BMP_COLOR_24: usize = 3;
 const BMP_COLOR_32: usize = 4;
+// BITMAPFILEHEADER plus the V4/V5 fields before bV4AlphaMask
+const BMP_ALPHA_MASK_OFFSET: usize = 14 + 52;
+const ACT_EXTENDED_SIZE: usize = 772;
+const TGA_HEADER_SIZE: usize = 18;
+const PCX_HEADER_SIZE: usize = 128;
+const PCX_PALETTE_SIZE: usize = 768;
+const PCX_RUN_MAX: usize = 0x3F;
+
+
+pub fn get_sprite(source_file: &PathBuf, format: SpriteFormat) -> SpriteData {
+	match format {
+		SpriteFormat::PNG => return get_png(source_file),
+		SpriteFormat::RAW => return get_raw(source_file),
+		SpriteFormat::BIN => return get_bin(source_file),
+		SpriteFormat::BMP => return get_bmp(source_file),
+		SpriteFormat::GIF => return get_gif(source_file),
+		SpriteFormat::TGA => return get_tga(source_file),
+		SpriteFormat::PCX => return get_pcx(source_file),
+		SpriteFormat::ACT => return get_act(source_file),
+		_ => {
+			println!("sprite_get::get_sprite() error: Invalid source format provided");
+			return SpriteData::default();
+		},
+	}
+}
 
 
 pub fn get_png(source_file: &PathBuf) -> SpriteData {
@@ -28,24 +55,94 @@ pub fn get_png(source_file: &PathBuf) -> SpriteData {
 		},
 	}
 	
-	let mut decoder = png::Decoder::new(file);
-	decoder.set_transformations(png::Transformations::STRIP_16);
-	let mut reader = decoder.read_info().unwrap();
+	// Keep 16-bit samples, they're reduced exactly below instead of truncated
+	let decoder = png::Decoder::new(file);
+	let mut reader: png::Reader<File>;
+	match decoder.read_info() {
+		Ok(value) => reader = value,
+		_ => {
+			println!("sprite_get::get_png() error: Could not read PNG header");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
 	
 	let mut palette: Vec<u8> = Vec::new();
 	
 	// Get bytes
 	let mut buffer = vec![0; reader.output_buffer_size()];
-	let frame = reader.next_frame(&mut buffer).unwrap();
+	let frame: png::OutputInfo;
+	match reader.next_frame(&mut buffer) {
+		Ok(value) => frame = value,
+		_ => {
+			println!("sprite_get::get_png() error: Could not decode PNG image data");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
 	
-	let source_bytes: Vec<u8> = buffer[..frame.buffer_size()].to_vec();
+	let mut metadata: SpriteMetadata = png_metadata(reader.info(), source_file);
+	let color_type: png::ColorType = frame.color_type;
+	let mut source_bytes: Vec<u8> = png_samples(&buffer[..frame.buffer_size()], frame.bit_depth);
+	let channels: usize = color_type.samples();
 	let mut pixel_vector: Vec<u8> = Vec::new();
 	
+	// Bit depth management. Only grayscale and indexed PNGs go below 8 bits, unpacked
+	// here so transparent gray keys are compared against whole samples.
+	let bit_depth: u16;
+	match frame.bit_depth {
+		png::BitDepth::One => {
+			bit_depth = 1;
+			source_bytes = sprite_transform::bpp_from_1(source_bytes, false);
+		},
+		
+		png::BitDepth::Two => {
+			bit_depth = 2;
+			source_bytes = sprite_transform::bpp_from_2(source_bytes, false);
+		},
+		
+		png::BitDepth::Four => {
+			bit_depth = 4;
+			source_bytes = sprite_transform::bpp_from_4(source_bytes, false);
+		},
+		
+		// 16-bit samples were already reduced to 8 bits
+		png::BitDepth::Eight | png::BitDepth::Sixteen => bit_depth = 8,
+	}
+	
+	// Transparent color key for grayscale and RGB, reduced to 8 bits like the samples
+	let mut color_key: Vec<u8> = Vec::new();
+	if color_type == png::ColorType::Grayscale || color_type == png::ColorType::Rgb {
+		// The decoder already narrows keys below 16 bits to one byte per channel
+		match &reader.info().trns {
+			Some(key) => color_key = png_samples(key, frame.bit_depth),
+			_ => (),
+		}
+		
+		if color_key.len() != channels {
+			color_key.clear();
+		}
+	}
+	
 	// Transfer color indices to pixel_vector
-	match reader.info().color_type {
+	match color_type {
 		png::ColorType::Grayscale => {
-			pixel_vector = source_bytes;
+			if color_key.is_empty() {
+				pixel_vector = source_bytes;
+			}
 			
+			else {
+				println!("Note: PNG has a transparent gray, will make it index 0");
+				println!("\tFile: {}", &source_file.display());
+				for pixel in 0..source_bytes.len() {
+					if source_bytes[pixel] == color_key[0] {
+						pixel_vector.push(0);
+					}
+					else {
+						pixel_vector.push(source_bytes[pixel]);
+					}
+				}
+			}
 		},
 		
 		png::ColorType::Indexed => {
@@ -53,7 +150,9 @@ pub fn get_png(source_file: &PathBuf) -> SpriteData {
 			match &reader.info().palette {
 				Some(pal_data) => {
 					let temp_pal: Vec<u8> = pal_data.to_vec();
-					let color_count: usize = temp_pal.len() / 3;
+					
+					// PLTE may hold fewer entries than the bit depth allows, pad with black
+					let color_count: usize = std::cmp::max(temp_pal.len() / 3, 2usize.pow(frame.bit_depth as u32));
 					let mut alpha_vec: Vec<u8> = Vec::new();
 					
 					match &reader.info().trns {
@@ -61,16 +160,19 @@ pub fn get_png(source_file: &PathBuf) -> SpriteData {
 							alpha_vec = alpha.to_vec();
 						},
 						
-						_ => (),
+						_ => metadata.alpha_filled = true,
 					}
 					
 					alpha_vec.resize(color_count, 0x80);
 					palette = vec![0; color_count * 4];
 					
 					for index in 0..color_count {
-						palette[4 * index + 0] = temp_pal[3 * index + 0];
-						palette[4 * index + 1] = temp_pal[3 * index + 1];
-						palette[4 * index + 2] = temp_pal[3 * index + 2];
+						if 3 * index + 2 < temp_pal.len() {
+							palette[4 * index + 0] = temp_pal[3 * index + 0];
+							palette[4 * index + 1] = temp_pal[3 * index + 1];
+							palette[4 * index + 2] = temp_pal[3 * index + 2];
+						}
+						
 						palette[4 * index + 3] = alpha_vec[index];
 					}
 				},
@@ -80,58 +182,136 @@ pub fn get_png(source_file: &PathBuf) -> SpriteData {
 		}
 		
 		png::ColorType::GrayscaleAlpha => {
-			println!("Note: PNG has color type grayscale with alpha, will discard alpha");
+			println!("Note: PNG has color type grayscale with alpha, will make fully transparent pixels index 0");
 			println!("\tFile: {}", &source_file.display());
 			for pixel in 0..source_bytes.len() / 2 {
-				pixel_vector.push(source_bytes[pixel * 2]);
+				if source_bytes[pixel * 2 + 1] == 0 {
+					pixel_vector.push(0);
+				}
+				else {
+					pixel_vector.push(source_bytes[pixel * 2]);
+				}
 			}
 		},
 		
-		png::ColorType::Rgb => {
-			println!("Note: PNG has color type RGB, will use red channel as grayscale");
-			println!("\tFile: {}", &source_file.display());
-			for pixel in 0..source_bytes.len() / 3 {
-				pixel_vector.push(source_bytes[pixel * 3]);
+		// Direct color gets a palette of its colors, without alpha they're PS2 opaque
+		png::ColorType::Rgb | png::ColorType::Rgba => {
+			let type_name: &str;
+			if channels == 4 {
+				type_name = "RGBA";
 			}
-		},
-		
-		png::ColorType::Rgba => {
-			println!("Note: PNG has color type RGBA, will use red channel as grayscale and discard alpha");
+			else {
+				type_name = "RGB";
+			}
+			
+			println!("Note: PNG has color type {}, will build a palette from its colors and make fully transparent pixels index 0", type_name);
 			println!("\tFile: {}", &source_file.display());
-			for pixel in 0..source_bytes.len() / 4 {
-				pixel_vector.push(source_bytes[pixel * 4]);
+			
+			let mut colors: Vec<u8> = Vec::with_capacity(source_bytes.len() / channels * 4);
+			
+			for pixel in 0..source_bytes.len() / channels {
+				let color: &[u8] = &source_bytes[pixel * channels..pixel * channels + channels];
+				colors.extend_from_slice(&color[..3]);
+				
+				if channels == 4 {
+					colors.push(color[3]);
+				}
+				else if !color_key.is_empty() && color == &color_key[..] {
+					colors.push(0);
+				}
+				else {
+					colors.push(0x80);
+				}
+			}
+			
+			metadata.alpha_filled = channels == 3;
+			
+			match sprite_transform::direct_to_indexed(&colors) {
+				Some((indices, color_table)) => {
+					pixel_vector = indices;
+					palette = color_table;
+				},
+				
+				None => {
+					println!("sprite_get::get_png() error: PNG has more than 256 colors and can't be made indexed");
+					println!("\tSkipped: {}", &source_file.display());
+					return SpriteData::default();
+				},
 			}
 		},
 	}
 
-	// Bit depth management
-	let mut bit_depth: u16 = 8;
-	match reader.info().bit_depth {
-		png::BitDepth::One => {
-			bit_depth = 1;
-			pixel_vector = sprite_transform::bpp_from_1(pixel_vector, false);
-		},
-		
-		png::BitDepth::Two => {
-			bit_depth = 2;
-			pixel_vector = sprite_transform::bpp_from_2(pixel_vector, false);
-		},
-		
-		png::BitDepth::Four => {
-			bit_depth = 4;
-			pixel_vector = sprite_transform::bpp_from_4(pixel_vector, false);
-		},
-		
-		_ => (),	// Hope and pray
-	}
-
 	return SpriteData {
-		width: reader.info().width as u16,
-		height: reader.info().height as u16,
+		width: frame.width as u16,
+		height: frame.height as u16,
 		bit_depth: bit_depth,
 		pixels: pixel_vector,
 		palette: palette,
+		metadata: metadata,
+	}
+}
+
+
+fn png_metadata(info: &png::Info, source_file: &PathBuf) -> SpriteMetadata {
+	let mut metadata: SpriteMetadata = SpriteMetadata::default();
+	let mut entries: Vec<(String, String)> = Vec::new();
+	
+	for chunk in &info.uncompressed_latin1_text {
+		entries.push((chunk.keyword.clone(), chunk.text.clone()));
+	}
+	
+	for chunk in &info.utf8_text {
+		match chunk.get_text() {
+			Ok(text) => entries.push((chunk.keyword.clone(), text)),
+			_ => (),
+		}
+	}
+	
+	// Provenance written by sprite_make::make_png()
+	for (keyword, text) in entries {
+		match &keyword as &str {
+			"ghoul-source" => metadata.source_path = text,
+			"ghoul-hash" => metadata.hash = u16::from_str_radix(text.trim_start_matches("0x"), 16).ok(),
+			"ghoul-tw" => metadata.tw = png_exponent(&keyword, &text, source_file),
+			"ghoul-th" => metadata.th = png_exponent(&keyword, &text, source_file),
+			"ghoul-compressed" => metadata.compressed = text.parse::<bool>().ok(),
+			"ghoul-version" => metadata.version = text,
+			_ => (),
+		}
 	}
+	
+	return metadata;
+}
+
+
+// tw/th from PNG text go through the same check as -tw/-th, bad values are dropped
+fn png_exponent(keyword: &str, text: &str, source_file: &PathBuf) -> Option<TextureExponent> {
+	match text.parse::<u16>() {
+		Ok(value) if bin_header::exponent_valid(TextureExponent(value)) => return Some(TextureExponent(value)),
+		
+		_ => {
+			println!("Warning: PNG has an invalid {} value ({}), it must be between 0 and {} and will be ignored", keyword, text, bin_header::TEXTURE_EXPONENT_MAX);
+			println!("\tFile: {}", &source_file.display());
+			return None;
+		},
+	}
+}
+
+
+fn png_samples(bytes: &[u8], bit_depth: png::BitDepth) -> Vec<u8> {
+	if bit_depth != png::BitDepth::Sixteen {
+		return bytes.to_vec();
+	}
+	
+	// Round 16-bit samples to the nearest 8-bit value, so 8-bit values scaled
+	// up by 257 (0x1212 from 0x12) come back exactly
+	let mut samples: Vec<u8> = Vec::with_capacity(bytes.len() / 2);
+	for sample in 0..bytes.len() / 2 {
+		let value: u32 = u16::from_be_bytes([bytes[sample * 2], bytes[sample * 2 + 1]]) as u32;
+		samples.push(((value * 255 + 32767) / 65535) as u8);
+	}
+	
+	return samples;
 }
 
 
@@ -140,7 +320,7 @@ pub fn get_raw(source_file: &PathBuf) -> SpriteData {
 	let mut width: u16 = 0;
 	let mut height: u16 = 0;
 	
-	let file_name: String = source_file.file_stem().unwrap().to_str().unwrap().to_lowercase();	
+	let file_name: String = source_file.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();	
 	let file_name_pieces: Vec<&str> = file_name.split("-").collect();
 	let piece_count: usize = file_name_pieces.len();
 	
@@ -177,6 +357,7 @@ pub fn get_raw(source_file: &PathBuf) -> SpriteData {
 				bit_depth: 8,
 				pixels: data,
 				palette: vec![],
+				metadata: SpriteMetadata::default(),
 			}
 		},
 		_ => {
@@ -200,47 +381,137 @@ pub fn get_bin(source_file: &PathBuf) -> SpriteData {
 		},
 	}
 	
+	if bin_data.len() < bin_header::HEADER_SIZE {
+		println!("sprite_get::get_bin() error: BIN file is smaller than its header");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
 	let header: BinHeader = bin_header::get_header(bin_data[0x0..0x10].to_vec());
 	
+	// Keep the header values so they can be written back out
+	let mut metadata: SpriteMetadata = SpriteMetadata {
+		source_path: source_file.display().to_string(),
+		hash: Some(header.hash),
+		tw: Some(header.tw),
+		th: Some(header.th),
+		compressed: Some(header.compressed),
+		version: String::new(),
+		alpha_filled: false,
+		raw_clut: None,
+	};
+	
+	let palette_length: usize;
+	
+	// Unknown clut values keep their table as it is, it goes back into BIN output
+	if bin_header::clut_entry_size(header.clut).is_none() {
+		match unknown_table_length(&bin_data, &header) {
+			Some(length) => {
+				println!("Warning: BIN has an unknown clut value (0x{:02X}), its {}-byte color table can't be read and will be kept as it is", header.clut, length);
+				println!("\tFile: {}", &source_file.display());
+				
+				metadata.raw_clut = Some((header.clut, bin_data[bin_header::HEADER_SIZE..bin_header::HEADER_SIZE + length].to_vec()));
+				palette_length = length;
+			},
+			
+			None => {
+				println!("Warning: BIN has an unknown clut value (0x{:02X}) and no color table could be found, reading it as having no palette, result may differ", header.clut);
+				println!("\tFile: {}", &source_file.display());
+				palette_length = 0;
+			},
+		}
+	}
+	
+	else {
+		palette_length = bin_header::palette_length(&header);
+	}
+	
+	if bin_data.len() < bin_header::HEADER_SIZE + palette_length {
+		println!("sprite_get::get_bin() error: BIN file ends inside its palette");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
 	if header.compressed {
-		return sprite_compress::decompress(bin_data, header);
+		let mut data: SpriteData = sprite_compress::decompress(bin_data, header, palette_length);
+		data.metadata = metadata;
+		return data;
 	}
 	
 	else {
-		let pointer: usize;
+		// Embedded palette, pointer moves past it
+		let pointer: usize = bin_header::HEADER_SIZE + palette_length;
+		let palette: Vec<u8> = bin_header::get_palette(&bin_data[bin_header::HEADER_SIZE..pointer], header.clut);
 		
-		// Embedded palette
-		let mut palette: Vec<u8> = Vec::new();
+		// Pixel data has to cover width x height at the declared depth, anything
+		// past it should be no more than padding up to the next 16 bytes
+		let byte_count: usize = bin_header::pixel_byte_count(&header);
+		let payload_length: usize = bin_data.len() - pointer;
 		
-		if header.clut == 0x20 {
-			// Get embedded palette
-			let color_count: usize = 2usize.pow(header.bit_depth as u32);
-			palette = vec![0; color_count * 4];
-			palette.copy_from_slice(&bin_data[0x10..0x10 + color_count * 4]);
-			
-			// Move pointer past palette
-			pointer = bin_header::HEADER_SIZE + (color_count * 4) as usize;
+		if payload_length < byte_count {
+			println!(
+				"sprite_get::get_bin() error: bad BIN: pixel data is {} bytes, {}x{} at {} bpp needs {}, result may differ",
+				payload_length, header.width, header.height, header.bit_depth, byte_count
+			);
+			println!("\tFile: {}", &source_file.display());
 		}
-		else {
-			// Move pointer past header
-			pointer = bin_header::HEADER_SIZE;
+		
+		else if payload_length - byte_count >= bin_header::FILE_ALIGNMENT {
+			println!("Warning: BIN has {} bytes past its pixel data, they will be ignored", payload_length - byte_count);
+			println!("\tFile: {}", &source_file.display());
 		}
 		
-		let mut pixels: Vec<u8> = vec![0; bin_data.len() - pointer];
-		pixels.copy_from_slice(&bin_data[pointer..]);
+		let mut pixels: Vec<u8> = bin_data[pointer..pointer + std::cmp::min(payload_length, byte_count)].to_vec();
 		
-		if header.bit_depth == 4 {
-			pixels = sprite_transform::bpp_from_4(pixels, true);
+		match header.bit_depth {
+			1 => pixels = sprite_transform::bpp_from_1(pixels, true),
+			2 => pixels = sprite_transform::bpp_from_2(pixels, true),
+			4 => pixels = sprite_transform::bpp_from_4(pixels, true),
+			_ => (),
 		}
 		
+		// Packed depths unpack to whole bytes, drop the pixels past the last one.
+		// Short pixel data was already reported, fill the rest with index 0.
+		pixels.resize(header.width as usize * header.height as usize, 0u8);
+		
 		return SpriteData {
 			width: header.width,
 			height: header.height,
 			bit_depth: header.bit_depth,
 			pixels: pixels,
 			palette: palette,
+			metadata: metadata,
+		}
+	}
+}
+
+
+// Tables of unknown clut values sit where a palette would, but their size isn't
+// known. Taken as 1 to 4 bytes per color, whichever leaves pixel data that ends the
+// file (give or take padding), or None if none does.
+fn unknown_table_length(bin_data: &[u8], header: &BinHeader) -> Option<usize> {
+	let color_count: usize = 2usize.pow(header.bit_depth as u32);
+	
+	for entry_size in 1..=4 {
+		let pointer: usize = bin_header::HEADER_SIZE + entry_size * color_count;
+		let body_length: usize;
+		
+		if header.compressed {
+			match sprite_compress::stream_length(bin_data, pointer, header) {
+				Some(length) => body_length = length,
+				None => continue,
+			}
+		}
+		else {
+			body_length = bin_header::pixel_byte_count(header);
+		}
+		
+		if pointer + body_length <= bin_data.len() && bin_data.len() - (pointer + body_length) < bin_header::FILE_ALIGNMENT {
+			return Some(entry_size * color_count);
 		}
 	}
+	
+	return None;
 }
 
 
@@ -279,33 +550,97 @@ pub fn get_bmp(source_file: &PathBuf) -> SpriteData {
 	let height: usize = dib_header.height.abs() as usize;
 	let bit_depth: usize = dib_header.bitcount as usize;
 	
-	// Cheers Wikipedia
-	let row_size: usize = ((bit_depth * width + 31) / 32) * 4;
-	let pixel_array_len: usize = row_size * height;
-	
 	let start: usize = file_header.bfOffBits as usize;
+	let mut pixel_array: Vec<u8>;
+	
+	if start >= bmp.contents.len() {
+		println!("sprite_get::get_bmp() error: Pixel data offset (bfOffBits) is past the end of the file");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
 	
-	let mut pixel_array: Vec<u8> = vec![0; pixel_array_len];
-	pixel_array.copy_from_slice(&bmp.contents[start..start + pixel_array_len]);
+	// Direct color gets a palette of its colors, like RGB PNGs
+	let mut direct_palette: Vec<u8> = Vec::new();
+	let mut direct_alpha_filled: bool = false;
 	
-	// Bit depth handling
-	match dib_header.bitcount {
-		1 => pixel_array = sprite_transform::bpp_from_1(pixel_array, false),
-		2 => pixel_array = sprite_transform::bpp_from_2(pixel_array, false),
-		4 => pixel_array = sprite_transform::bpp_from_4(pixel_array, false),
-		8 => (),
-		_ => {
-			println!("Warning: Skipping BMP as its color depth is not supported ({})", bit_depth);
+	let compression: String = dib_header.compression.clone().unwrap_or_default();
+	match (&compression as &str, bit_depth) {
+		// RLE streams decode straight to one byte per pixel, upside-down without padding
+		("BI_RLE8", 8) => pixel_array = bmp_rle_decode(&bmp.contents[start..], width, height, false),
+		("BI_RLE4", 4) => pixel_array = bmp_rle_decode(&bmp.contents[start..], width, height, true),
+		
+		("BI_RLE8", _) | ("BI_RLE4", _) => {
+			println!("sprite_get::get_bmp() error: {} does not match color depth ({})", compression, bit_depth);
 			println!("\tSkipped: {}", &source_file.display());
 			return SpriteData::default();
 		},
+		
+		_ => {
+			// Cheers Wikipedia
+			let row_size: usize = ((bit_depth * width + 31) / 32) * 4;
+			let pixel_array_len: usize = row_size * height;
+			
+			if start + pixel_array_len > bmp.contents.len() {
+				println!("sprite_get::get_bmp() error: BMP file ends inside its pixel data");
+				println!("\tSkipped: {}", &source_file.display());
+				return SpriteData::default();
+			}
+			
+			pixel_array = vec![0; pixel_array_len];
+			pixel_array.copy_from_slice(&bmp.contents[start..start + pixel_array_len]);
+			
+			// Bit depth handling
+			match dib_header.bitcount {
+				1 => pixel_array = sprite_transform::bpp_from_1(pixel_array, false),
+				2 => pixel_array = sprite_transform::bpp_from_2(pixel_array, false),
+				4 => pixel_array = sprite_transform::bpp_from_4(pixel_array, false),
+				8 => (),
+				
+				16 | 24 | 32 => {
+					println!("Note: BMP has {}-bit direct color, will build a palette from its colors", bit_depth);
+					println!("\tFile: {}", &source_file.display());
+					
+					let masks: [u32; 4] = bmp_masks(&bmp, &dib_header);
+					let colors: Vec<u8> = bmp_direct_colors(pixel_array, width, height, bit_depth / 8, masks);
+					
+					match sprite_transform::direct_to_indexed(&colors) {
+						Some((indices, color_table)) => {
+							pixel_array = indices;
+							direct_palette = color_table;
+						},
+						
+						None => {
+							println!("sprite_get::get_bmp() error: BMP has more than 256 colors and can't be made indexed");
+							println!("\tSkipped: {}", &source_file.display());
+							return SpriteData::default();
+						},
+					}
+					
+					direct_alpha_filled = masks[3] == 0;
+				},
+				
+				_ => {
+					println!("Warning: Skipping BMP as its color depth is not supported ({})", bit_depth);
+					println!("\tSkipped: {}", &source_file.display());
+					return SpriteData::default();
+				},
+			}
+		},
 	}
 	
-	// Trim padding
+	// Trim padding, bottom-up unless height is negative
 	let expanded_width: usize = pixel_array.len() / height;
 	let mut pixel_vector: Vec<u8> = Vec::new();
 	
-	for y in (0..height).rev() {
+	let rows: Vec<usize>;
+	if dib_header.height < 0 {
+		rows = (0..height).collect();
+	}
+	else {
+		rows = (0..height).rev().collect();
+	}
+	
+	for y in rows {
 		for x in 0..width {
 			pixel_vector.push(pixel_array[y * expanded_width + x]);
 		}
@@ -324,12 +659,28 @@ pub fn get_bmp(source_file: &PathBuf) -> SpriteData {
 		pixel_vector.resize(width * height, 0u8);
 	}
 	
+	// Direct color has no color table of its own
+	if bit_depth > 8 {
+		return SpriteData {
+			width: width as u16,
+			height: height as u16,
+			bit_depth: 8,
+			pixels: pixel_vector,
+			palette: direct_palette,
+			metadata: SpriteMetadata {
+				alpha_filled: direct_alpha_filled,
+				..SpriteMetadata::default()
+			},
+		}
+	}
+	
 	// Palette read
+	// Bit masks only follow BITMAPINFOHEADER, later headers include them
 	let flags_offset: usize;
 	match dib_header.compression {
 		Some(value) => match &value as &str {
-			"BI_BITFIELDS" => flags_offset = 12,
-			"BI_ALPHABITFIELDS" => flags_offset = 16,
+			"BI_BITFIELDS" if dib_header.size == 40 => flags_offset = 12,
+			"BI_ALPHABITFIELDS" if dib_header.size == 40 => flags_offset = 16,
 			_ => flags_offset = 0,
 		},
 		
@@ -357,6 +708,14 @@ pub fn get_bmp(source_file: &PathBuf) -> SpriteData {
 		None => color_count = 2u16.pow(bit_depth as u32) as usize,
 	}
 	
+	// V4/V5 BMPs with an alpha mask (bV4AlphaMask) store palette alpha in the RGBQUAD
+	// reserved byte, like the ones ghoul writes
+	let mut stored_alpha: bool = false;
+	if dib_header.size >= 108 && bmp.contents.len() >= BMP_ALPHA_MASK_OFFSET + 4 {
+		let alpha_mask: u32 = u32::from_le_bytes(bmp.contents[BMP_ALPHA_MASK_OFFSET..BMP_ALPHA_MASK_OFFSET + 4].try_into().unwrap());
+		stored_alpha = alpha_mask != 0;
+	}
+	
 	// Create and populate palette
 	let mut palette: Vec<u8> = vec![0; color_count * 4];
 	
@@ -365,8 +724,12 @@ pub fn get_bmp(source_file: &PathBuf) -> SpriteData {
 		palette[4 * color + 1] = bmp.contents[index + (color_size * color + 1)];
 		palette[4 * color + 2] = bmp.contents[index + (color_size * color + 0)];
 		
-		// 24 bit BMP input will use default +R alpha
-		if color % 32 == 0 || (color as i32 - 8) % 32 == 0 && color != 8 {
+		if stored_alpha {
+			palette[4 * color + 3] = bmp.contents[index + (color_size * color + 3)];
+		}
+		
+		// Other BMP input will use default +R alpha
+		else if color % 32 == 0 || (color as i32 - 8) % 32 == 0 && color != 8 {
 			palette[4 * color + 3] = 0x00;
 		}
 		else {
@@ -380,5 +743,997 @@ pub fn get_bmp(source_file: &PathBuf) -> SpriteData {
 		bit_depth: bit_depth as u16,
 		pixels: pixel_vector,
 		palette: palette,
+		metadata: SpriteMetadata {
+			alpha_filled: !stored_alpha,
+			..SpriteMetadata::default()
+		},
+	}
+}
+
+// Red, green, blue and alpha masks of direct color pixels, alpha is 0 if there isn't any
+fn bmp_masks(bmp: &BMP, dib_header: &DIBHEADER) -> [u32; 4] {
+	let compression: String = dib_header.compression.clone().unwrap_or_default();
+	
+	// Custom masks, inside V4/V5 headers or right after BITMAPINFOHEADER
+	if compression == "BI_BITFIELDS" || compression == "BI_ALPHABITFIELDS" {
+		if dib_header.size > 40 {
+			return [
+				dib_header.RedMask.unwrap_or(0), dib_header.GreenMask.unwrap_or(0),
+				dib_header.BlueMask.unwrap_or(0), dib_header.AlphaMask.unwrap_or(0),
+			];
+		}
+		
+		let mask_count: usize;
+		if compression == "BI_ALPHABITFIELDS" {
+			mask_count = 4;
+		}
+		else {
+			mask_count = 3;
+		}
+		
+		let mut masks: [u32; 4] = [0; 4];
+		for mask in 0..mask_count {
+			let offset: usize = 14 + dib_header.size as usize + 4 * mask;
+			if bmp.contents.len() >= offset + 4 {
+				masks[mask] = u32::from_le_bytes(bmp.contents[offset..offset + 4].try_into().unwrap());
+			}
+		}
+		
+		return masks;
+	}
+	
+	// BI_RGB, 16 bit is X1R5G5B5, 24 and 32 bit are BGR(X)
+	if dib_header.bitcount == 16 {
+		return [0x7C00, 0x03E0, 0x001F, 0];
+	}
+	
+	return [0x00FF0000, 0x0000FF00, 0x000000FF, 0];
+}
+
+
+// Direct color pixels as RGBA, without row padding. Colors without alpha of their
+// own get PS2 opaque (0x80).
+fn bmp_direct_colors(pixel_array: Vec<u8>, width: usize, height: usize, pixel_size: usize, masks: [u32; 4]) -> Vec<u8> {
+	let mut output: Vec<u8> = Vec::with_capacity(width * height * 4);
+	let row_size: usize = pixel_array.len() / height;
+	
+	for y in 0..height {
+		for x in 0..width {
+			let start: usize = y * row_size + x * pixel_size;
+			let mut value: u32 = 0;
+			
+			for byte in 0..pixel_size {
+				value |= (pixel_array[start + byte] as u32) << (8 * byte);
+			}
+			
+			// Scale each channel to 8 bits
+			for channel in 0..4 {
+				let shift: u32 = masks[channel].trailing_zeros() % 32;
+				let maximum: u32 = masks[channel] >> shift;
+				
+				if maximum == 0 {
+					if channel == 3 {
+						output.push(0x80);
+					}
+					else {
+						output.push(0);
+					}
+				}
+				else {
+					output.push((((value & masks[channel]) >> shift) as u64 * 255 / maximum as u64) as u8);
+				}
+			}
+		}
+	}
+	
+	return output;
+}
+
+
+fn bmp_rle_decode(stream: &[u8], width: usize, height: usize, four_bit: bool) -> Vec<u8> {
+	let mut pixels: Vec<u8> = vec![0; width * height];
+	let mut position: usize = 0;
+	let mut x: usize = 0;
+	let mut y: usize = 0;
+	
+	while position + 1 < stream.len() {
+		let count: usize = stream[position] as usize;
+		let value: u8 = stream[position + 1];
+		position += 2;
+		
+		// Encoded mode, repeat value (or alternate its two nibbles)
+		if count > 0 {
+			for pixel in 0..count {
+				if x < width && y < height {
+					if !four_bit {
+						pixels[y * width + x] = value;
+					}
+					else if pixel % 2 == 0 {
+						pixels[y * width + x] = value >> 4;
+					}
+					else {
+						pixels[y * width + x] = value & 0xF;
+					}
+				}
+				
+				x += 1;
+			}
+			
+			continue;
+		}
+		
+		match value {
+			// End of line
+			0 => {
+				x = 0;
+				y += 1;
+			},
+			
+			// End of bitmap
+			1 => break,
+			
+			// Delta, move right and up
+			2 => {
+				if position + 1 >= stream.len() {
+					break;
+				}
+				
+				x += stream[position] as usize;
+				y += stream[position + 1] as usize;
+				position += 2;
+			},
+			
+			// Absolute mode, padded to a 16-bit boundary
+			_ => {
+				let length: usize = value as usize;
+				let byte_count: usize;
+				if four_bit {
+					byte_count = (length + 1) / 2;
+				}
+				else {
+					byte_count = length;
+				}
+				
+				if position + byte_count > stream.len() {
+					break;
+				}
+				
+				for pixel in 0..length {
+					if x < width && y < height {
+						if !four_bit {
+							pixels[y * width + x] = stream[position + pixel];
+						}
+						else if pixel % 2 == 0 {
+							pixels[y * width + x] = stream[position + pixel / 2] >> 4;
+						}
+						else {
+							pixels[y * width + x] = stream[position + pixel / 2] & 0xF;
+						}
+					}
+					
+					x += 1;
+				}
+				
+				position += byte_count + byte_count % 2;
+			},
+		}
+	}
+	
+	return pixels;
+}
+
+
+pub fn get_act(source_file: &PathBuf) -> SpriteData {
+	// Palette only, no pixels
+	let act_data: Vec<u8>;
+	match fs::read(source_file) {
+		Ok(value) => act_data = value,
+		_ => {
+			println!("sprite_get::get_act() error: ACT file read error");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	let mut color_count: usize = std::cmp::min(act_data.len() / 3, 256);
+	let mut transparent_index: usize = usize::MAX;
+	
+	// Extended ACT: color count and transparent index follow the color table
+	if act_data.len() >= ACT_EXTENDED_SIZE {
+		color_count = u16::from_be_bytes([act_data[0x300], act_data[0x301]]) as usize;
+		transparent_index = u16::from_be_bytes([act_data[0x302], act_data[0x303]]) as usize;
+		
+		if color_count == 0 || color_count > 256 {
+			color_count = 256;
+		}
+	}
+	
+	let mut palette: Vec<u8> = vec![0; color_count * 4];
+	
+	for color in 0..color_count {
+		palette[4 * color + 0] = act_data[3 * color + 0];
+		palette[4 * color + 1] = act_data[3 * color + 1];
+		palette[4 * color + 2] = act_data[3 * color + 2];
+		
+		if color == transparent_index {
+			palette[4 * color + 3] = 0x00;
+		}
+		else {
+			palette[4 * color + 3] = 0xFF;
+		}
+	}
+	
+	return SpriteData {
+		width: 0,
+		height: 0,
+		bit_depth: 8,
+		pixels: vec![],
+		palette: palette,
+		metadata: SpriteMetadata::default(),
 	}
-}
\ No newline at end of file
+}
+
+
+pub fn get_gif(source_file: &PathBuf) -> SpriteData {
+	let file: File;
+	match File::open(&source_file) {
+		Ok(value) => file = value,
+		_ => {
+			println!("sprite_get::get_gif() error: GIF file open error");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	let mut options = gif::DecodeOptions::new();
+	options.set_color_output(gif::ColorOutput::Indexed);
+	
+	let mut decoder: gif::Decoder<File>;
+	match options.read_info(file) {
+		Ok(value) => decoder = value,
+		_ => {
+			println!("sprite_get::get_gif() error: Could not read GIF header");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	let width: usize = decoder.width() as usize;
+	let height: usize = decoder.height() as usize;
+	let mut rgb_palette: Vec<u8> = Vec::new();
+	
+	match decoder.global_palette() {
+		Some(pal_data) => rgb_palette = pal_data.to_vec(),
+		_ => (),
+	}
+	
+	// Only the first frame is used
+	let frame: &gif::Frame;
+	match decoder.read_next_frame() {
+		Ok(Some(value)) => frame = value,
+		_ => {
+			println!("sprite_get::get_gif() error: Could not read GIF frame");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	match &frame.palette {
+		Some(pal_data) => rgb_palette = pal_data.to_vec(),
+		_ => (),
+	}
+	
+	// Frames can be smaller than the image, place it on a transparent canvas
+	let background: u8 = frame.transparent.unwrap_or(0);
+	let mut pixel_vector: Vec<u8> = vec![background; width * height];
+	
+	for y in 0..frame.height as usize {
+		for x in 0..frame.width as usize {
+			let canvas_x: usize = frame.left as usize + x;
+			let canvas_y: usize = frame.top as usize + y;
+			
+			if canvas_x < width && canvas_y < height {
+				pixel_vector[canvas_y * width + canvas_x] = frame.buffer[y * frame.width as usize + x];
+			}
+		}
+	}
+	
+	// GIF transparency becomes +R alpha
+	let color_count: usize = rgb_palette.len() / 3;
+	let mut palette: Vec<u8> = vec![0; color_count * 4];
+	
+	for color in 0..color_count {
+		palette[4 * color + 0] = rgb_palette[3 * color + 0];
+		palette[4 * color + 1] = rgb_palette[3 * color + 1];
+		palette[4 * color + 2] = rgb_palette[3 * color + 2];
+		
+		if frame.transparent == Some(color as u8) {
+			palette[4 * color + 3] = 0x00;
+		}
+		else {
+			palette[4 * color + 3] = 0x80;
+		}
+	}
+	
+	let bit_depth: u16;
+	match color_count {
+		1..=2 => bit_depth = 1,
+		3..=4 => bit_depth = 2,
+		5..=16 => bit_depth = 4,
+		_ => bit_depth = 8,
+	}
+	
+	return SpriteData {
+		width: width as u16,
+		height: height as u16,
+		bit_depth: bit_depth,
+		pixels: pixel_vector,
+		palette: palette,
+		metadata: SpriteMetadata {
+			alpha_filled: true,
+			..SpriteMetadata::default()
+		},
+	}
+}
+
+
+pub fn get_tga(source_file: &PathBuf) -> SpriteData {
+	let tga_data: Vec<u8>;
+	match fs::read(source_file) {
+		Ok(value) => tga_data = value,
+		_ => {
+			println!("sprite_get::get_tga() error: TGA file read error");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	if tga_data.len() < TGA_HEADER_SIZE {
+		println!("sprite_get::get_tga() error: TGA file too short");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	// Header reads
+	let id_length: usize = tga_data[0x00] as usize;
+	let color_map_type: u8 = tga_data[0x01];
+	let image_type: u8 = tga_data[0x02];
+	let color_map_first: usize = u16::from_le_bytes([tga_data[0x03], tga_data[0x04]]) as usize;
+	let color_map_length: usize = u16::from_le_bytes([tga_data[0x05], tga_data[0x06]]) as usize;
+	let color_map_entry_size: usize = tga_data[0x07] as usize;
+	let width: usize = u16::from_le_bytes([tga_data[0x0C], tga_data[0x0D]]) as usize;
+	let height: usize = u16::from_le_bytes([tga_data[0x0E], tga_data[0x0F]]) as usize;
+	let pixel_depth: u8 = tga_data[0x10];
+	let descriptor: u8 = tga_data[0x11];
+	
+	// Color-mapped (1) and RLE color-mapped (9) only
+	if color_map_type != 1 || (image_type != 1 && image_type != 9) || pixel_depth != 8 {
+		println!("Warning: Skipping TGA as only 8-bit color-mapped TGAs are supported (type {}, {} bits)", image_type, pixel_depth);
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	if color_map_first + color_map_length > 256 {
+		println!("Warning: Skipping TGA as its color map has more than 256 colors");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	let entry_bytes: usize = (color_map_entry_size + 7) / 8;
+	let mut pointer: usize = TGA_HEADER_SIZE + id_length;
+	
+	if pointer + color_map_length * entry_bytes > tga_data.len() {
+		println!("sprite_get::get_tga() error: TGA color map is truncated");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	// Color map, entries start at color_map_first
+	let color_count: usize = color_map_first + color_map_length;
+	let mut palette: Vec<u8> = vec![0; color_count * 4];
+	
+	for entry in 0..color_map_length {
+		let color: usize = color_map_first + entry;
+		let source: usize = pointer + entry * entry_bytes;
+		
+		match color_map_entry_size {
+			// ARRRRRGG GGGBBBBB, little endian
+			15 | 16 => {
+				let value: u16 = u16::from_le_bytes([tga_data[source], tga_data[source + 1]]);
+				palette[4 * color + 0] = (((value >> 10) & 0x1F) * 255 / 31) as u8;
+				palette[4 * color + 1] = (((value >> 5) & 0x1F) * 255 / 31) as u8;
+				palette[4 * color + 2] = (((value >> 0) & 0x1F) * 255 / 31) as u8;
+			},
+			
+			24 | 32 => {
+				palette[4 * color + 0] = tga_data[source + 2];
+				palette[4 * color + 1] = tga_data[source + 1];
+				palette[4 * color + 2] = tga_data[source + 0];
+			},
+			
+			_ => {
+				println!("Warning: Skipping TGA as its color map entry size is not supported ({})", color_map_entry_size);
+				println!("\tSkipped: {}", &source_file.display());
+				return SpriteData::default();
+			},
+		}
+		
+		// 32-bit entries carry alpha, others use default +R alpha
+		if color_map_entry_size == 32 {
+			palette[4 * color + 3] = tga_data[source + 3];
+		}
+		else if color % 32 == 0 || (color as i32 - 8) % 32 == 0 && color != 8 {
+			palette[4 * color + 3] = 0x00;
+		}
+		else {
+			palette[4 * color + 3] = 0x80;
+		}
+	}
+	
+	pointer += color_map_length * entry_bytes;
+	
+	// Pixel data
+	let pixel_count: usize = width * height;
+	let mut pixel_array: Vec<u8> = Vec::with_capacity(pixel_count);
+	
+	if image_type == 9 {
+		while pixel_array.len() < pixel_count && pointer < tga_data.len() {
+			let packet: u8 = tga_data[pointer];
+			let count: usize = (packet & 0x7F) as usize + 1;
+			pointer += 1;
+			
+			// Run-length packet
+			if packet & 0x80 != 0 {
+				if pointer >= tga_data.len() {
+					break;
+				}
+				
+				for _pixel in 0..count {
+					pixel_array.push(tga_data[pointer]);
+				}
+				
+				pointer += 1;
+			}
+			
+			// Raw packet
+			else {
+				let end: usize = std::cmp::min(pointer + count, tga_data.len());
+				pixel_array.extend_from_slice(&tga_data[pointer..end]);
+				pointer = end;
+			}
+		}
+	}
+	
+	else {
+		let end: usize = std::cmp::min(pointer + pixel_count, tga_data.len());
+		pixel_array.extend_from_slice(&tga_data[pointer..end]);
+	}
+	
+	if pixel_array.len() != pixel_count {
+		println!("sprite_get::get_tga() error: bad TGA: pixel count mismatches image dimensions, result may differ");
+		println!("\tFile: {}", &source_file.display());
+		pixel_array.resize(pixel_count, 0u8);
+	}
+	
+	// Bottom-left origin unless bit 5 is set, right-to-left if bit 4 is set
+	let top_down: bool = descriptor & 0x20 != 0;
+	let right_to_left: bool = descriptor & 0x10 != 0;
+	let mut pixel_vector: Vec<u8> = Vec::with_capacity(pixel_count);
+	
+	for row in 0..height {
+		let y: usize;
+		if top_down {
+			y = row;
+		}
+		else {
+			y = height - 1 - row;
+		}
+		
+		for column in 0..width {
+			let x: usize;
+			if right_to_left {
+				x = width - 1 - column;
+			}
+			else {
+				x = column;
+			}
+			
+			pixel_vector.push(pixel_array[y * width + x]);
+		}
+	}
+	
+	let bit_depth: u16;
+	match color_count {
+		1..=2 => bit_depth = 1,
+		3..=4 => bit_depth = 2,
+		0 | 5..=16 => bit_depth = 4,
+		_ => bit_depth = 8,
+	}
+	
+	return SpriteData {
+		width: width as u16,
+		height: height as u16,
+		bit_depth: bit_depth,
+		pixels: pixel_vector,
+		palette: palette,
+		metadata: SpriteMetadata {
+			alpha_filled: color_map_entry_size != 32,
+			..SpriteMetadata::default()
+		},
+	}
+}
+
+
+pub fn get_pcx(source_file: &PathBuf) -> SpriteData {
+	let pcx_data: Vec<u8>;
+	match fs::read(source_file) {
+		Ok(value) => pcx_data = value,
+		_ => {
+			println!("sprite_get::get_pcx() error: PCX file read error");
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	if pcx_data.len() < PCX_HEADER_SIZE || pcx_data[0x00] != 0x0A {
+		println!("sprite_get::get_pcx() error: Not a PCX file");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	// Header reads
+	let encoding: u8 = pcx_data[0x02];
+	let bits_per_plane: usize = pcx_data[0x03] as usize;
+	let x_min: usize = u16::from_le_bytes([pcx_data[0x04], pcx_data[0x05]]) as usize;
+	let y_min: usize = u16::from_le_bytes([pcx_data[0x06], pcx_data[0x07]]) as usize;
+	let x_max: usize = u16::from_le_bytes([pcx_data[0x08], pcx_data[0x09]]) as usize;
+	let y_max: usize = u16::from_le_bytes([pcx_data[0x0A], pcx_data[0x0B]]) as usize;
+	let planes: usize = pcx_data[0x41] as usize;
+	let bytes_per_line: usize = u16::from_le_bytes([pcx_data[0x42], pcx_data[0x43]]) as usize;
+	
+	if x_max < x_min || y_max < y_min {
+		println!("sprite_get::get_pcx() error: bad PCX: invalid image dimensions");
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	let width: usize = x_max - x_min + 1;
+	let height: usize = y_max - y_min + 1;
+	
+	if width > u16::MAX as usize || height > u16::MAX as usize {
+		println!("sprite_get::get_pcx() error: bad PCX: image is {}x{}, maximum is {}x{}", width, height, u16::MAX, u16::MAX);
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	// 8, 4, 2 or 1 bpp chunky, or 4 bpp as four 1-bit planes
+	let bit_depth: u16;
+	match (bits_per_plane, planes) {
+		(8, 1) => bit_depth = 8,
+		(4, 1) | (1, 4) => bit_depth = 4,
+		(2, 1) => bit_depth = 2,
+		(1, 1) => bit_depth = 1,
+		_ => {
+			println!("Warning: Skipping PCX as its color depth is not supported ({} bits, {} planes)", bits_per_plane, planes);
+			println!("\tSkipped: {}", &source_file.display());
+			return SpriteData::default();
+		},
+	}
+	
+	// Every plane's scanline has to hold a full row
+	if bytes_per_line * 8 / bits_per_plane < width {
+		println!("sprite_get::get_pcx() error: bad PCX: {} bytes per line can't hold {} pixels", bytes_per_line, width);
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	
+	// Scanline decode
+	let scanline_size: usize = planes * bytes_per_line;
+	let image_size: usize = scanline_size * height;
+	
+	// Most the rest of the file could decode to, every two bytes making a full run if
+	// compressed. Anything bigger is a broken header rather than a short file.
+	let stored_size: usize = pcx_data.len() - PCX_HEADER_SIZE;
+	let decoded_size_max: usize;
+	if encoding == 1 {
+		decoded_size_max = (stored_size / 2 + stored_size % 2) * PCX_RUN_MAX;
+	}
+	else {
+		decoded_size_max = stored_size;
+	}
+	
+	if image_size > decoded_size_max {
+		println!("sprite_get::get_pcx() error: bad PCX: image needs {} bytes but the file can hold at most {}", image_size, decoded_size_max);
+		println!("\tSkipped: {}", &source_file.display());
+		return SpriteData::default();
+	}
+	let mut image_data: Vec<u8> = Vec::with_capacity(image_size);
+	let mut pointer: usize = PCX_HEADER_SIZE;
+	
+	if encoding == 1 {
+		while image_data.len() < image_size && pointer < pcx_data.len() {
+			let byte: u8 = pcx_data[pointer];
+			pointer += 1;
+			
+			// Top two bits set, run of the next byte
+			if byte & 0xC0 == 0xC0 {
+				if pointer >= pcx_data.len() {
+					break;
+				}
+				
+				for _count in 0..(byte & 0x3F) {
+					image_data.push(pcx_data[pointer]);
+				}
+				
+				pointer += 1;
+			}
+			
+			else {
+				image_data.push(byte);
+			}
+		}
+	}
+	
+	else {
+		let end: usize = std::cmp::min(pointer + image_size, pcx_data.len());
+		image_data.extend_from_slice(&pcx_data[pointer..end]);
+	}
+	
+	if image_data.len() < image_size {
+		println!("sprite_get::get_pcx() error: bad PCX: pixel count mismatches image dimensions, result may differ");
+		println!("\tFile: {}", &source_file.display());
+	}
+	
+	image_data.resize(image_size, 0u8);
+	
+	// Get pixels
+	let mut pixel_vector: Vec<u8> = Vec::with_capacity(width * height);
+	
+	for y in 0..height {
+		let scanline: &[u8] = &image_data[y * scanline_size..(y + 1) * scanline_size];
+		let row: Vec<u8>;
+		
+		match (bits_per_plane, planes) {
+			(1, 4) => {
+				// One bit per plane, plane 0 is the lowest bit
+				let mut planar_row: Vec<u8> = vec![0; bytes_per_line * 8];
+				
+				for plane in 0..4 {
+					let plane_bits: Vec<u8> = sprite_transform::bpp_from_1(scanline[plane * bytes_per_line..(plane + 1) * bytes_per_line].to_vec(), false);
+					
+					for x in 0..plane_bits.len() {
+						planar_row[x] |= plane_bits[x] << plane;
+					}
+				}
+				
+				row = planar_row;
+			},
+			
+			(4, 1) => row = sprite_transform::bpp_from_4(scanline.to_vec(), false),
+			(2, 1) => row = sprite_transform::bpp_from_2(scanline.to_vec(), false),
+			(1, 1) => row = sprite_transform::bpp_from_1(scanline.to_vec(), false),
+			_ => row = scanline.to_vec(),
+		}
+		
+		pixel_vector.extend_from_slice(&row[..std::cmp::min(width, row.len())]);
+		
+		for _x in row.len()..width {
+			pixel_vector.push(0x00);
+		}
+	}
+	
+	// Palette read, 256 colors after a 0x0C marker at the end of the file,
+	// 16 colors in the header otherwise
+	let color_count: usize = 2usize.pow(bit_depth as u32);
+	let palette_start: usize;
+	
+	if bit_depth == 8 {
+		if pcx_data.len() > PCX_HEADER_SIZE + PCX_PALETTE_SIZE && pcx_data[pcx_data.len() - PCX_PALETTE_SIZE - 1] == 0x0C {
+			palette_start = pcx_data.len() - PCX_PALETTE_SIZE;
+		}
+		
+		else {
+			println!("Warning: PCX has no 256-color palette, will use grayscale");
+			println!("\tFile: {}", &source_file.display());
+			
+			return SpriteData {
+				width: width as u16,
+				height: height as u16,
+				bit_depth: bit_depth,
+				pixels: pixel_vector,
+				palette: vec![],
+				metadata: SpriteMetadata::default(),
+			}
+		}
+	}
+	
+	else {
+		palette_start = 0x10;
+	}
+	
+	let mut palette: Vec<u8> = vec![0; color_count * 4];
+	
+	for color in 0..color_count {
+		palette[4 * color + 0] = pcx_data[palette_start + 3 * color + 0];
+		palette[4 * color + 1] = pcx_data[palette_start + 3 * color + 1];
+		palette[4 * color + 2] = pcx_data[palette_start + 3 * color + 2];
+		
+		// PCX palettes have no alpha, use default +R alpha
+		if color % 32 == 0 || (color as i32 - 8) % 32 == 0 && color != 8 {
+			palette[4 * color + 3] = 0x00;
+		}
+		else {
+			palette[4 * color + 3] = 0x80;
+		}
+	}
+	
+	return SpriteData {
+		width: width as u16,
+		height: height as u16,
+		bit_depth: bit_depth,
+		pixels: pixel_vector,
+		palette: palette,
+		metadata: SpriteMetadata {
+			alpha_filled: true,
+			..SpriteMetadata::default()
+		},
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_file::TempFile;
+	
+	fn temp_file(name: &str, contents: &[u8]) -> TempFile {
+		return TempFile::new(name, contents);
+	}
+	
+	// Uncompressed 8 bpp PCX header
+	fn pcx_header(x_max: u16, y_max: u16, bytes_per_line: u16) -> Vec<u8> {
+		let mut header: Vec<u8> = vec![0; PCX_HEADER_SIZE];
+		header[0x00] = 0x0A;
+		header[0x03] = 8;
+		header[0x08..0x0A].copy_from_slice(&x_max.to_le_bytes());
+		header[0x0A..0x0C].copy_from_slice(&y_max.to_le_bytes());
+		header[0x41] = 1;
+		header[0x42..0x44].copy_from_slice(&bytes_per_line.to_le_bytes());
+		return header;
+	}
+	
+	// Grayscale PNG with a transparent gray, samples packed at the given depth
+	fn gray_png(name: &str, bit_depth: png::BitDepth, width: u32, samples: &[u8], key: u8) -> TempFile {
+		let bits: usize = bit_depth as usize;
+		let mut packed: Vec<u8> = vec![0; (samples.len() * bits + 7) / 8];
+		
+		for sample in 0..samples.len() {
+			let bit: usize = sample * bits;
+			packed[bit / 8] |= samples[sample] << (8 - bits - bit % 8);
+		}
+		
+		let mut png_data: Vec<u8> = Vec::new();
+		{
+			let mut encoder = png::Encoder::new(&mut png_data, width, 1);
+			encoder.set_color(png::ColorType::Grayscale);
+			encoder.set_depth(bit_depth);
+			encoder.set_trns(vec![0, key]);
+			let mut writer = encoder.write_header().unwrap();
+			writer.write_image_data(&packed).unwrap();
+		}
+		
+		return temp_file(name, &png_data);
+	}
+	
+	#[test]
+	fn png_gray_key_1bpp() {
+		let data: SpriteData = get_png(&gray_png("gray1.png", png::BitDepth::One, 8, &[1, 0, 1, 1, 0, 0, 1, 0], 1));
+		
+		assert_eq!(data.bit_depth, 1);
+		assert_eq!(data.pixels, vec![0, 0, 0, 0, 0, 0, 0, 0]);
+	}
+	
+	#[test]
+	fn png_gray_key_2bpp() {
+		let data: SpriteData = get_png(&gray_png("gray2.png", png::BitDepth::Two, 4, &[2, 1, 2, 3], 2));
+		
+		assert_eq!(data.bit_depth, 2);
+		assert_eq!(data.pixels, vec![0, 1, 0, 3]);
+	}
+	
+	#[test]
+	fn png_gray_key_4bpp() {
+		let data: SpriteData = get_png(&gray_png("gray4.png", png::BitDepth::Four, 4, &[3, 5, 3, 15], 3));
+		
+		assert_eq!(data.bit_depth, 4);
+		assert_eq!(data.pixels, vec![0, 5, 0, 15]);
+	}
+	
+	// Uncompressed BIN the way make_bin lays it out. These tests only check that ghoul
+	// reads back what it writes, there are no original uncompressed game BINs to compare to.
+	fn uncompressed_bin(width: u16, height: u16, bit_depth: u16, pixels: &[u8], palette: &[u8]) -> Vec<u8> {
+		let clut: u16;
+		if palette.is_empty() {
+			clut = bin_header::CLUT_NONE;
+		}
+		else {
+			clut = bin_header::CLUT_32;
+		}
+		
+		let mut bin_data: Vec<u8> = bin_header::get_bytes(BinHeader {
+			compressed: false,
+			clut: clut,
+			bit_depth: bit_depth,
+			width: width,
+			height: height,
+			tw: bin_header::TextureExponent(0),
+			th: bin_header::TextureExponent(0),
+			hash: 0,
+		});
+		
+		bin_data.extend_from_slice(palette);
+		bin_data.extend_from_slice(&crate::sprite_make::uncompressed_pixels(pixels.to_vec(), bit_depth));
+		return bin_data;
+	}
+	
+	#[test]
+	fn uncompressed_bin_odd_width_4bpp_layout() {
+		// Rows aren't aligned, pixel 3 (second row) shares a byte with pixel 2
+		let bin_data: Vec<u8> = uncompressed_bin(3, 2, 4, &[1, 2, 3, 4, 5, 6], &[]);
+		
+		assert_eq!(bin_data.len(), bin_header::HEADER_SIZE + 3);
+		assert_eq!(&bin_data[bin_header::HEADER_SIZE..], &[0x21, 0x43, 0x65]);
+	}
+	
+	#[test]
+	fn uncompressed_bin_round_trip() {
+		for bit_depth in [1u16, 2, 4, 8] {
+			let color_count: usize = 1 << bit_depth;
+			let pixels: Vec<u8> = (0..5 * 3).map(|pixel| (pixel * 7 % color_count) as u8).collect();
+			let palette: Vec<u8> = (0..color_count * 4).map(|byte| byte as u8).collect();
+			
+			let bin_data: Vec<u8> = uncompressed_bin(5, 3, bit_depth, &pixels, &palette);
+			let data: SpriteData = get_bin(&temp_file(&format!("round_trip_{}.bin", bit_depth), &bin_data));
+			
+			assert_eq!((data.width, data.height, data.bit_depth), (5, 3, bit_depth));
+			assert_eq!(data.pixels, pixels);
+			assert_eq!(data.palette, palette);
+		}
+	}
+	
+	#[test]
+	fn pcx_reads_small_image() {
+		let mut pcx_data: Vec<u8> = pcx_header(2, 1, 4);
+		pcx_data.extend_from_slice(&[1, 2, 3, 0, 4, 5, 6, 0]);
+		
+		let data: SpriteData = get_pcx(&temp_file("small.pcx", &pcx_data));
+		
+		assert_eq!((data.width, data.height), (3, 2));
+		assert_eq!(data.pixels, vec![1, 2, 3, 4, 5, 6]);
+	}
+	
+	#[test]
+	fn pcx_rejects_width_past_u16() {
+		let mut pcx_data: Vec<u8> = pcx_header(0xFFFF, 0, 0xFFFF);
+		pcx_data.extend_from_slice(&[0; 16]);
+		
+		let data: SpriteData = get_pcx(&temp_file("wide.pcx", &pcx_data));
+		
+		assert_eq!(data.width, 0);
+	}
+	
+	#[test]
+	fn pcx_rejects_scanlines_past_file_length() {
+		let mut pcx_data: Vec<u8> = pcx_header(0, 0xFFFE, 0xFFFE);
+		pcx_data.extend_from_slice(&[0; 16]);
+		
+		let data: SpriteData = get_pcx(&temp_file("long.pcx", &pcx_data));
+		
+		assert_eq!(data.width, 0);
+	}
+	
+	#[test]
+	fn pcx_rejects_short_bytes_per_line() {
+		let mut pcx_data: Vec<u8> = pcx_header(7, 0, 4);
+		pcx_data.extend_from_slice(&[0; 16]);
+		
+		let data: SpriteData = get_pcx(&temp_file("narrow.pcx", &pcx_data));
+		
+		assert_eq!(data.width, 0);
+	}
+	
+	#[test]
+	fn unknown_clut_table_is_kept() {
+		let header: BinHeader = BinHeader {
+			compressed: false,
+			clut: 0x18,
+			bit_depth: 4,
+			width: 4,
+			height: 2,
+			tw: TextureExponent(0),
+			th: TextureExponent(0),
+			hash: 0,
+		};
+		
+		// 3 bytes for each of 16 colors, then 4 bytes of 4 bpp pixels
+		let table: Vec<u8> = (0..48).collect();
+		let mut bin_data: Vec<u8> = bin_header::get_bytes(header);
+		bin_data.extend_from_slice(&table);
+		bin_data.extend_from_slice(&[0x21, 0x43, 0x65, 0x87]);
+		
+		let data: SpriteData = get_bin(&temp_file("unknown_clut.bin", &bin_data));
+		
+		assert!(data.palette.is_empty());
+		assert_eq!(data.pixels, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+		assert_eq!(data.metadata.raw_clut, Some((0x18, table)));
+	}
+	
+	#[test]
+	fn png_rgb_builds_palette() {
+		let mut png_data: Vec<u8> = Vec::new();
+		{
+			let mut encoder = png::Encoder::new(&mut png_data, 4, 1);
+			encoder.set_color(png::ColorType::Rgb);
+			encoder.set_depth(png::BitDepth::Eight);
+			encoder.set_trns(vec![0, 0xFF, 0, 0, 0, 0xFF]);
+			let mut writer = encoder.write_header().unwrap();
+			writer.write_image_data(&[200, 10, 10, 0xFF, 0, 0xFF, 10, 200, 10, 200, 10, 10]).unwrap();
+		}
+		
+		let data: SpriteData = get_png(&temp_file("rgb.png", &png_data));
+		
+		assert_eq!(data.bit_depth, 8);
+		assert_eq!(data.pixels, vec![1, 0, 2, 1]);
+		assert_eq!(&data.palette[..12], &[0, 0, 0, 0, 200, 10, 10, 0x80, 10, 200, 10, 0x80]);
+		assert!(data.metadata.alpha_filled);
+	}
+	
+	#[test]
+	fn bmp_24bit_builds_palette() {
+		// 2x2 bottom-up, BGR with each row padded to 8 bytes
+		let pixel_data: [u8; 16] = [
+			0, 0, 255, 0, 255, 0, 0, 0,
+			255, 0, 0, 0, 0, 255, 0, 0,
+		];
+		
+		let mut bmp_data: Vec<u8> = vec![b'B', b'M'];
+		bmp_data.extend_from_slice(&(54 + pixel_data.len() as u32).to_le_bytes());
+		bmp_data.extend_from_slice(&[0, 0, 0, 0]);
+		bmp_data.extend_from_slice(&54u32.to_le_bytes());
+		bmp_data.extend_from_slice(&40u32.to_le_bytes());
+		bmp_data.extend_from_slice(&2i32.to_le_bytes());
+		bmp_data.extend_from_slice(&2i32.to_le_bytes());
+		bmp_data.extend_from_slice(&1u16.to_le_bytes());
+		bmp_data.extend_from_slice(&24u16.to_le_bytes());
+		bmp_data.resize(54, 0);
+		bmp_data.extend_from_slice(&pixel_data);
+		
+		let data: SpriteData = get_bmp(&temp_file("direct.bmp", &bmp_data));
+		
+		// Colors are indexed bottom row first (red, green), then blue. Nothing is
+		// transparent, so red gets index 0.
+		assert_eq!((data.width, data.height, data.bit_depth), (2, 2, 8));
+		assert_eq!(data.pixels, vec![2, 0, 0, 1]);
+		assert_eq!(&data.palette[..12], &[255, 0, 0, 0x80, 0, 255, 0, 0x80, 0, 0, 255, 0x80]);
+		assert!(data.metadata.alpha_filled);
+	}
+	
+	#[test]
+	fn bmp_pixel_offset_past_end() {
+		let mut bmp_data: Vec<u8> = vec![b'B', b'M'];
+		bmp_data.extend_from_slice(&62u32.to_le_bytes());
+		bmp_data.extend_from_slice(&[0, 0, 0, 0]);
+		bmp_data.extend_from_slice(&4096u32.to_le_bytes());
+		bmp_data.extend_from_slice(&40u32.to_le_bytes());
+		bmp_data.extend_from_slice(&2i32.to_le_bytes());
+		bmp_data.extend_from_slice(&2i32.to_le_bytes());
+		bmp_data.extend_from_slice(&1u16.to_le_bytes());
+		bmp_data.extend_from_slice(&8u16.to_le_bytes());
+		bmp_data.extend_from_slice(&1u32.to_le_bytes());
+		bmp_data.resize(62, 0);
+		
+		let data: SpriteData = get_bmp(&temp_file("far_offset.bmp", &bmp_data));
+		
+		assert_eq!(data.width, 0);
+	}
+}
@@ -0,0 +1,150 @@
+use std::fs;
+
+use crate::{
+	PathBuf,
+	Parameters,
+	SpriteData,
+	SpriteFormat,
+	get_directory_files,
+	type_matches,
+	bin_header,
+	bin_header::{BinHeader, TextureExponent},
+	sprite_get,
+};
+
+
+pub fn info(parameters: Parameters) {
+	let file_list: Vec<PathBuf>;
+	
+	if parameters.directory_mode {
+		file_list = get_directory_files(&parameters.source_path);
+	}
+	
+	else {
+		file_list = vec![parameters.source_path.clone()];
+	}
+	
+	let mut sprite_count: usize = 0;
+	let mut mismatch_count: usize = 0;
+	
+	for file in file_list {
+		if !type_matches(file.extension(), parameters.source_format) {
+			continue;
+		}
+		
+		let format: SpriteFormat = parameters.source_format;
+		
+		if format == SpriteFormat::BIN {
+			match bin_info(&file) {
+				Some(matches) => {
+					sprite_count += 1;
+					if !matches {
+						mismatch_count += 1;
+					}
+				},
+				
+				None => (),
+			}
+		}
+		
+		else if sprite_info(&file, format) {
+			sprite_count += 1;
+		}
+		
+		println!();
+	}
+	
+	println!("{} sprites, {} with tw/th that don't match their dimensions.", sprite_count, mismatch_count);
+}
+
+
+fn palette_text(palette: &Vec<u8>) -> String {
+	if palette.is_empty() {
+		return "no palette (grayscale)".to_string();
+	}
+	
+	return format!("{} colors", palette.len() / 4);
+}
+
+
+fn exponent_text(exponent: TextureExponent) -> String {
+	if bin_header::exponent_valid(exponent) {
+		return format!("{} ({}px)", exponent.0, 1 << exponent.0);
+	}
+	
+	return format!("{}", exponent.0);
+}
+
+
+// Returns whether tw/th match the sprite's dimensions
+fn bin_info(file: &PathBuf) -> Option<bool> {
+	let bin_data: Vec<u8>;
+	match fs::read(file) {
+		Ok(value) => bin_data = value,
+		_ => {
+			println!("sprite_info::bin_info() error: BIN file read error");
+			println!("\tSkipped: {}", &file.display());
+			return None;
+		},
+	}
+	
+	if bin_data.len() < bin_header::HEADER_SIZE {
+		println!("sprite_info::bin_info() error: BIN file is smaller than its header");
+		println!("\tSkipped: {}", &file.display());
+		return None;
+	}
+	
+	let header: BinHeader = bin_header::get_header(bin_data[0x0..0x10].to_vec());
+	
+	let compression: &str;
+	if header.compressed {
+		compression = "compressed";
+	}
+	else {
+		compression = "uncompressed";
+	}
+	
+	println!("{}", file.display());
+	println!("    BIN, {}x{}, {} bpp, {}, clut 0x{:02X}", header.width, header.height, header.bit_depth, compression, header.clut);
+	println!("    tw {}, th {}, hash 0x{:04X}", exponent_text(header.tw), exponent_text(header.th), header.hash);
+	
+	if bin_header::clut_entry_size(header.clut).is_none() {
+		println!("    Warning: unknown clut value, the palette can't be read");
+	}
+	
+	if !bin_header::exponent_valid(header.tw) || !bin_header::exponent_valid(header.th) {
+		println!("    Warning: tw/th above {} can't be a PS2 texture page", bin_header::TEXTURE_EXPONENT_MAX);
+	}
+	
+	if !bin_header::texture_matches(&header) {
+		println!(
+			"    Warning: tw/th don't fit the sprite's dimensions, expected at least tw {} and th {}",
+			bin_header::fit_exponent(header.width).0, bin_header::fit_exponent(header.height).0
+		);
+		return Some(false);
+	}
+	
+	return Some(true);
+}
+
+
+fn sprite_info(file: &PathBuf, format: SpriteFormat) -> bool {
+	let data: SpriteData = sprite_get::get_sprite(file, format);
+	
+	if data.width == 0 || data.height == 0 {
+		return false;
+	}
+	
+	let format_name: String = file.extension().unwrap_or_default().to_string_lossy().to_uppercase();
+	
+	println!("{}", file.display());
+	println!("    {}, {}x{}, {} bpp, {}", format_name, data.width, data.height, data.bit_depth, palette_text(&data.palette));
+	
+	// PNGs made from BINs carry the BIN's header values
+	match (data.metadata.tw, data.metadata.th) {
+		(Some(tw), Some(th)) => println!("    tw {}, th {} from '{}'", tw.0, th.0, data.metadata.source_path),
+		_ => (),
+	}
+	
+	return true;
+}
@@ -1,24 +1,29 @@
 use std::io::{Write, BufWriter};
 use std::fs::File;
+use std::ffi::OsString;
 
 use crate::{
 	PathBuf,
 	Parameters,
 	SpriteData,
 	SpriteFormat,
+	shared_types::BmpHeader,
 	shared_types::HashMode,
 	shared_types::CompressedData,
 	bin_header,
+	bin_header::TextureExponent,
 	sprite_compress,
 	sprite_transform,
 };
 
+const PCX_HEADER_SIZE: usize = 128;
 
-fn overwrite_blocked(target_path: &PathBuf, overwrite: bool) -> bool {
+
+pub fn overwrite_blocked(target_path: &PathBuf, overwrite: bool) -> bool {
 	if !overwrite {
 		match target_path.try_exists() {
 			Ok(true) => {
-				println!("'{}' already exists. Use -w to allow overwriting files.", target_path.to_str().unwrap());
+				println!("'{}' already exists. Use -w to allow overwriting files.", target_path.display());
 				return true;
 			},
 			
@@ -36,7 +41,33 @@ fn overwrite_blocked(target_path: &PathBuf, overwrite: bool) -> bool {
 }	
 
 
-fn bmp_header(width: u16, height: u16, bit_depth: u16) -> Vec<u8> {
+// Source file stem followed by the suffix, left as an OsString so names that aren't
+// valid UTF-8 come through untouched
+pub fn stem_with_suffix(source_path: &PathBuf, suffix: &str) -> OsString {
+	let mut file_name: OsString = source_path.file_stem().unwrap_or_default().to_os_string();
+	file_name.push(suffix);
+	return file_name;
+}
+
+
+pub fn get_target_path(parameters: &Parameters, extension: &str) -> PathBuf {
+	let mut target_path: PathBuf = parameters.target_path.clone();
+	target_path.push(stem_with_suffix(&parameters.source_path, &format!("{}.{}", parameters.target_suffix, extension)));
+	return target_path;
+}
+
+
+fn bmp_dib_size(header: BmpHeader) -> u32 {
+	match header {
+		BmpHeader::CORE => return 12,
+		BmpHeader::INFO => return 40,
+		BmpHeader::V4 => return 108,
+		BmpHeader::V5 => return 124,
+	}
+}
+
+
+fn bmp_header(width: u16, height: u16, bit_depth: u16, header: BmpHeader, compression: u32, image_size: u32, alpha: bool) -> Vec<u8> {
 	let mut bmp_data: Vec<u8> = Vec::new();
 	
 	// BITMAPFILEHEADER
@@ -46,12 +77,20 @@ fn bmp_header(width: u16, height: u16, bit_depth: u16) -> Vec<u8> {
 	
 	// 4 bytes, size of the bitmap in bytes
 	// 14 bytes - BITMAPFILEHEADER
-	// 12 bytes - DIBHEADER of type BITMAPCOREHEADER
-	let header_length: u32 = 14 + 12 + 2u32.pow(bit_depth as u32) * 3;
-	let bmp_file_size: [u8; 4] = (header_length + (width + width % 4) as u32 * height as u32).to_le_bytes();
-	for byte in 0..4 {
-		bmp_data.push(bmp_file_size[byte]);
+	// 12/40/108/124 bytes - DIBHEADER
+	// RGBTRIPLE color table for BITMAPCOREHEADER, RGBQUAD otherwise
+	let dib_size: u32 = bmp_dib_size(header);
+	let color_size: u32;
+	if header == BmpHeader::CORE {
+		color_size = 3;
 	}
+	else {
+		color_size = 4;
+	}
+	
+	let color_count: u32 = 2u32.pow(bit_depth as u32);
+	let header_length: u32 = 14 + dib_size + color_count * color_size;
+	bmp_data.extend_from_slice(&(header_length + image_size).to_le_bytes());
 	
 	// 2 bytes each for bfReserved1 and 2
 	bmp_data.push(0x00);
@@ -60,25 +99,23 @@ fn bmp_header(width: u16, height: u16, bit_depth: u16) -> Vec<u8> {
 	bmp_data.push(0x00);
 	
 	// 4 bytes, offset to pixel array
-	bmp_data.push((header_length & 0xFF) as u8);
-	bmp_data.push((header_length >> 8) as u8);
-	bmp_data.push(0x00);
-	bmp_data.push(0x00);
+	bmp_data.extend_from_slice(&header_length.to_le_bytes());
 	
-	// DIBHEADER (BITMAPCOREHEADER)
-	// 4 bytes, 12
-	bmp_data.push(0x0C);
-	bmp_data.push(0x00);
-	bmp_data.push(0x00);
-	bmp_data.push(0x00);
+	// DIBHEADER
+	// 4 bytes, header size
+	bmp_data.extend_from_slice(&dib_size.to_le_bytes());
 	
-	// 2 bytes, image width
-	bmp_data.push(width as u8);
-	bmp_data.push((width >> 8) as u8);
+	// BITMAPCOREHEADER, 2 bytes each for width and height
+	if header == BmpHeader::CORE {
+		bmp_data.extend_from_slice(&width.to_le_bytes());
+		bmp_data.extend_from_slice(&height.to_le_bytes());
+	}
 	
-	// 2 bytes, image height
-	bmp_data.push(height as u8);
-	bmp_data.push((height >> 8) as u8);
+	// Everything else, 4 bytes each
+	else {
+		bmp_data.extend_from_slice(&(width as u32).to_le_bytes());
+		bmp_data.extend_from_slice(&(height as i32).to_le_bytes());
+	}
 	
 	// 2 bytes, planes
 	bmp_data.push(0x01);
@@ -96,15 +133,67 @@ fn bmp_header(width: u16, height: u16, bit_depth: u16) -> Vec<u8> {
 	}
 
 	bmp_data.push(0x00);
+	
+	if header == BmpHeader::CORE {
+		return bmp_data;
+	}
+	
+	// BITMAPINFOHEADER
+	// 4 bytes, compression (BI_RGB, BI_RLE8, BI_RLE4)
+	bmp_data.extend_from_slice(&compression.to_le_bytes());
+	
+	// 4 bytes, size of the pixel array
+	bmp_data.extend_from_slice(&image_size.to_le_bytes());
+	
+	// 4 bytes each, horizontal and vertical resolution (72 DPI)
+	bmp_data.extend_from_slice(&2835u32.to_le_bytes());
+	bmp_data.extend_from_slice(&2835u32.to_le_bytes());
+	
+	// 4 bytes each, colors used and important colors
+	bmp_data.extend_from_slice(&color_count.to_le_bytes());
+	bmp_data.extend_from_slice(&0u32.to_le_bytes());
+	
+	if header == BmpHeader::INFO {
+		return bmp_data;
+	}
+	
+	// BITMAPV4HEADER
+	// 4 bytes each, red, green, blue and alpha masks. Indexed images don't use them
+	// for pixels, but a non-zero alpha mask marks the color table alpha as stored.
+	if alpha {
+		bmp_data.extend_from_slice(&0x00FF0000u32.to_le_bytes());
+		bmp_data.extend_from_slice(&0x0000FF00u32.to_le_bytes());
+		bmp_data.extend_from_slice(&0x000000FFu32.to_le_bytes());
+		bmp_data.extend_from_slice(&0xFF000000u32.to_le_bytes());
+	}
+	else {
+		bmp_data.extend_from_slice(&[0u8; 16]);
+	}
+	
+	// 4 bytes, color space, "sRGB" stored little-endian
+	bmp_data.extend_from_slice(&[0x42, 0x47, 0x52, 0x73]);
+	
+	// 36 bytes, CIEXYZTRIPLE endpoints, 12 bytes gamma (unused for sRGB)
+	bmp_data.extend_from_slice(&[0u8; 48]);
+	
+	if header == BmpHeader::V4 {
+		return bmp_data;
+	}
+	
+	// BITMAPV5HEADER
+	// 4 bytes, rendering intent (LCS_GM_IMAGES)
+	bmp_data.extend_from_slice(&4u32.to_le_bytes());
+	
+	// 4 bytes each, profile data offset, profile size, reserved
+	bmp_data.extend_from_slice(&[0u8; 12]);
+	
 	return bmp_data;
 }
 
 
 pub fn make_png(parameters: Parameters, data: SpriteData) {
 	// Set target filename
-	let mut target_path: PathBuf = parameters.target_path;
-	target_path.push(parameters.source_path.file_stem().unwrap());
-	target_path.set_extension("png");
+	let target_path: PathBuf = get_target_path(&parameters, "png");
 	
 	if overwrite_blocked(&target_path, parameters.overwrite) {
 		return
@@ -125,39 +214,178 @@ pub fn make_png(parameters: Parameters, data: SpriteData) {
 	let ref mut buffer = BufWriter::new(png_file);
 	let mut encoder = png::Encoder::new(buffer, data.width as u32, data.height as u32);
 	
-	// 4 bpp handling
-	let mut working_pixels: Vec<u8>;
+	let (rgb_palette, transparency) = png_palette(&data);
+	
+	// Provenance, lets a BIN made from this PNG restore the original header
+	let _ = encoder.add_text_chunk("ghoul-version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+	
+	if !data.metadata.source_path.is_empty() {
+		let _ = encoder.add_itxt_chunk("ghoul-source".to_string(), data.metadata.source_path.clone());
+	}
+	
+	match data.metadata.hash {
+		Some(hash) => { let _ = encoder.add_text_chunk("ghoul-hash".to_string(), format!("0x{:04X}", hash)); },
+		None => (),
+	}
+	
+	match data.metadata.tw {
+		Some(tw) => { let _ = encoder.add_text_chunk("ghoul-tw".to_string(), tw.0.to_string()); },
+		None => (),
+	}
+	
+	match data.metadata.th {
+		Some(th) => { let _ = encoder.add_text_chunk("ghoul-th".to_string(), th.0.to_string()); },
+		None => (),
+	}
+	
+	match data.metadata.compressed {
+		Some(compressed) => { let _ = encoder.add_text_chunk("ghoul-compressed".to_string(), compressed.to_string()); },
+		None => (),
+	}
+	
+	let (working_pixels, png_depth) = png_pixels(data);
+	
+	encoder.set_depth(png_depth);
+	encoder.set_color(png::ColorType::Indexed);
+	encoder.set_palette(rgb_palette);
+	encoder.set_trns(transparency);
+	
+	let mut writer = encoder.write_header().expect("sprite_make::make_png() error: Could not write PNG header");
+	writer.write_image_data(&working_pixels).unwrap();
+}
+
+
+pub fn make_png_animation(parameters: Parameters, mut frames: Vec<SpriteData>) {
+	let target_path: PathBuf = get_target_path(&parameters, "png");
+	
+	if overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+	
+	// APNG frames share one size, bit depth, palette and tRNS
+	let mut width: u16 = 0;
+	let mut height: u16 = 0;
+	let mut bit_depth: u16 = 0;
+	
+	for frame in 0..frames.len() {
+		width = std::cmp::max(width, frames[frame].width);
+		height = std::cmp::max(height, frames[frame].height);
+		bit_depth = std::cmp::max(bit_depth, frames[frame].bit_depth);
+	}
+	
+	let color_count: usize = 2usize.pow(bit_depth as u32);
+	let mut shared_palette: Vec<u8> = frames[0].palette.clone();
+	
+	if !shared_palette.is_empty() {
+		shared_palette.resize(color_count * 4, 0u8);
+	}
+	
+	for frame in 1..frames.len() {
+		if frames[frame].palette[..] != frames[0].palette[..] {
+			println!("Note: Animation frames use different palettes, using the first frame's palette");
+			break;
+		}
+	}
+	
+	// Frames are placed at the top left corner of the largest frame
+	for frame in 0..frames.len() {
+		let frame_width: usize = frames[frame].width as usize;
+		let mut canvas: Vec<u8> = vec![0; width as usize * height as usize];
+		
+		for y in 0..frames[frame].height as usize {
+			let row_start: usize = y * frame_width;
+			canvas[y * width as usize..y * width as usize + frame_width].copy_from_slice(&frames[frame].pixels[row_start..row_start + frame_width]);
+		}
+		
+		frames[frame].pixels = canvas;
+		frames[frame].width = width;
+		frames[frame].height = height;
+		frames[frame].bit_depth = bit_depth;
+		frames[frame].palette = shared_palette.clone();
+	}
+	
+	let png_file: File;
+	match File::create(&target_path) {
+		Ok(file) => png_file = file,
+		_ => {
+			println!("sprite_make::make_png_animation() error: Could not create target PNG file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	let buffer = BufWriter::new(png_file);
+	let mut encoder = png::Encoder::new(buffer, width as u32, height as u32);
+	
+	let (rgb_palette, transparency) = png_palette(&frames[0]);
+	
+	encoder.set_color(png::ColorType::Indexed);
+	encoder.set_palette(rgb_palette);
+	encoder.set_trns(transparency);
+	
+	let _ = encoder.set_animated(frames.len() as u32, 0);
+	let _ = encoder.set_frame_delay(std::cmp::min(parameters.frame_delay, u16::MAX as u32) as u16, 1000);
+	let _ = encoder.set_dispose_op(png::DisposeOp::None);
+	let _ = encoder.set_blend_op(png::BlendOp::Source);
+	
+	let mut png_depth: png::BitDepth = png::BitDepth::Eight;
+	let mut frame_pixels: Vec<Vec<u8>> = Vec::with_capacity(frames.len());
+	
+	for frame in frames {
+		let (working_pixels, depth) = png_pixels(frame);
+		png_depth = depth;
+		frame_pixels.push(working_pixels);
+	}
+	
+	encoder.set_depth(png_depth);
+	
+	let mut writer = encoder.write_header().expect("sprite_make::make_png_animation() error: Could not write PNG header");
+	
+	for frame in 0..frame_pixels.len() {
+		match writer.write_image_data(&frame_pixels[frame]) {
+			Ok(_) => (),
+			_ => {
+				println!("sprite_make::make_png_animation() error: Could not write frame {}", frame);
+				println!("\tFile: {}", &target_path.display());
+				return;
+			},
+		}
+	}
+	
+	let _ = writer.finish();
+}
+
+
+// Indexed pixel data, packed for PNG output
+fn png_pixels(data: SpriteData) -> (Vec<u8>, png::BitDepth) {
+	let working_pixels: Vec<u8>;
 	
 	match data.bit_depth {
-		// 1 and 2 bpp not currently in use
-		// 1 => {
-			// working_pixels = sprite_transform::bpp_to_1(data.pixels, false);
-			// encoder.set_depth(png::BitDepth::One);
-		// },
+		1 => {
+			working_pixels = sprite_transform::align_to_byte(data.pixels, data.height as usize, 1);
+			return (sprite_transform::bpp_to_1(working_pixels, false), png::BitDepth::One);
+		},
 
-		// 2 => {
-			// working_pixels = sprite_transform::bpp_to_2(data.pixels, false);
-			// encoder.set_depth(png::BitDepth::Two);
-		// },
+		2 => {
+			working_pixels = sprite_transform::align_to_byte(data.pixels, data.height as usize, 2);
+			return (sprite_transform::bpp_to_2(working_pixels, false), png::BitDepth::Two);
+		},
 		
 		4 => {
-			working_pixels = sprite_transform::align_to_4(data.pixels, data.height as usize);
-			working_pixels = sprite_transform::bpp_to_4(working_pixels, false);
-			encoder.set_depth(png::BitDepth::Four);
+			working_pixels = sprite_transform::align_to_byte(data.pixels, data.height as usize, 4);
+			return (sprite_transform::bpp_to_4(working_pixels, false), png::BitDepth::Four);
 		},
 		
-		8 => {
-			working_pixels = data.pixels;
-			encoder.set_depth(png::BitDepth::Eight);
-		},
+		8 => return (data.pixels, png::BitDepth::Eight),
 		
 		// Shouldn't happen
-		_ => panic!("sprite_make::make_png() error: Invalid bit depth"),
+		_ => panic!("sprite_make::png_pixels() error: Invalid bit depth"),
 	}
-	
-	encoder.set_color(png::ColorType::Indexed);
-	
-	// Palette
+}
+
+
+// PLTE and tRNS contents
+fn png_palette(data: &SpriteData) -> (Vec<u8>, Vec<u8>) {
 	let color_count: usize = 2usize.pow(data.bit_depth as u32);
 	let mut rgb_palette: Vec<u8> = Vec::with_capacity(color_count * 3);
 	let mut transparency: Vec<u8> = vec![0xFF; color_count];
@@ -181,12 +409,8 @@ pub fn make_png(parameters: Parameters, data: SpriteData) {
 			transparency[color] = 0xFF;
 		}
 	}
-			
-	encoder.set_palette(rgb_palette);
-	encoder.set_trns(transparency);
 	
-	let mut writer = encoder.write_header().expect("sprite_make::make_png() error: Could not write PNG header");
-	writer.write_image_data(&working_pixels).unwrap();
+	return (rgb_palette, transparency);
 }
 
 
@@ -198,8 +422,7 @@ pub fn make_raw(parameters: Parameters, data: SpriteData) {
 	}
 	
 	else {
-		let file_stem: &str = parameters.source_path.file_stem().unwrap().to_str().unwrap();
-		target_path.push(format!("{}-W-{}-H-{}.raw", file_stem, data.width, data.height));
+		target_path.push(stem_with_suffix(&parameters.source_path, &format!("-W-{}-H-{}.raw", data.width, data.height)));
 	}
 	
 	if overwrite_blocked(&target_path, parameters.overwrite) {
@@ -228,11 +451,22 @@ pub fn make_raw(parameters: Parameters, data: SpriteData) {
 }
 
 
-pub fn make_bin(parameters: Parameters, mut data: SpriteData) {
+// Pixels packed across rows the way 1.0.0 wrote them, lowest bits first and without
+// per-row alignment, so an odd-width 4 bpp row shares its last byte with the next row.
+// Not yet compared against original uncompressed game BINs, the game may align rows.
+pub fn uncompressed_pixels(pixels: Vec<u8>, bit_depth: u16) -> Vec<u8> {
+	match bit_depth {
+		1 => return sprite_transform::bpp_to_1(pixels, true),
+		2 => return sprite_transform::bpp_to_2(pixels, true),
+		4 => return sprite_transform::bpp_to_4(pixels, true),
+		_ => return pixels,
+	}
+}
+
+
+pub fn make_bin(parameters: Parameters, data: SpriteData) {
 	// Set target filename
-	let mut target_path = parameters.target_path;
-	target_path.push(parameters.source_path.file_stem().unwrap());
-	target_path.set_extension("bin");
+	let target_path: PathBuf = get_target_path(&parameters, "bin");
 	
 	if overwrite_blocked(&target_path, parameters.overwrite) {
 		return;
@@ -250,13 +484,29 @@ pub fn make_bin(parameters: Parameters, mut data: SpriteData) {
 		},
 	}
 	
-	// Header
+	// Header. An unknown clut's table is written back as it is when nothing replaced
+	// it and the sprite still has the color count it was read with.
 	let clut: u16;
-	if data.palette.is_empty() {
-		clut = 0x0000;
+	let mut palette: Vec<u8> = data.palette.clone();
+	let color_count: usize = 2usize.pow(data.bit_depth as u32);
+	
+	match &data.metadata.raw_clut {
+		Some((value, table)) if palette.is_empty() && table.len() % color_count == 0 && table.len() <= 4 * color_count => {
+			clut = *value;
+			palette = table.clone();
+		},
+		
+		_ if palette.is_empty() => clut = bin_header::CLUT_NONE,
+		_ => clut = bin_header::CLUT_32,
 	}
-	else {
-		clut = 0x0020;
+	
+	// tw/th come from -tw/-th, then -tex-fit, then the source BIN (or a PNG made from one) when known
+	let mut tw: TextureExponent = data.metadata.tw.unwrap_or(TextureExponent(0));
+	let mut th: TextureExponent = data.metadata.th.unwrap_or(TextureExponent(0));
+	
+	if parameters.fit_texture {
+		tw = bin_header::fit_exponent(data.width);
+		th = bin_header::fit_exponent(data.height);
 	}
 	
 	let mut bin_header = bin_header::BinHeader {
@@ -265,22 +515,19 @@ pub fn make_bin(parameters: Parameters, mut data: SpriteData) {
 		bit_depth: data.bit_depth,
 		width: data.width,
 		height: data.height,
-		tw: 0x0,
-		th: 0x0,
+		tw: parameters.tw_override.unwrap_or(tw),
+		th: parameters.th_override.unwrap_or(th),
 		hash: parameters.hash_value,
 	};
 	
-	let palette: Vec<u8> = data.palette.clone();
+	let source_hash: Option<u16> = data.metadata.hash;
 	
 	// Contents
 	let mut sprite_contents: Vec<u8> = Vec::new();
 	
 	// Uncompressed mode
 	if parameters.uncompressed {
-		if data.bit_depth == 4 {
-			data.pixels = sprite_transform::bpp_to_4(data.pixels, true);
-		}
-		sprite_contents.extend_from_slice(&data.pixels[..]);
+		sprite_contents = uncompressed_pixels(data.pixels, data.bit_depth);
 	}
 	
 	// Compressed mode
@@ -307,9 +554,11 @@ pub fn make_bin(parameters: Parameters, mut data: SpriteData) {
 		}
 	}
 	
-	// Generate hash
+	// Generate hash, unless the source had one and -hash-gen wasn't given
 	match parameters.hash_mode {
-		HashMode::GENERATE => {
+		HashMode::GENERATE if source_hash.is_some() => bin_header.hash = source_hash.unwrap(),
+		
+		HashMode::GENERATE | HashMode::REGENERATE => {
 			let mut hash: u16 = 0;
 			
 			for byte in 0..sprite_contents.len() / 2 {
@@ -340,19 +589,305 @@ pub fn make_bin(parameters: Parameters, mut data: SpriteData) {
 
 pub fn make_bmp(parameters: Parameters, data: SpriteData) {
 	// Set target filename
-	let mut target_path: PathBuf = parameters.target_path;
-	target_path.push(parameters.source_path.file_stem().unwrap());
-	target_path.set_extension("bmp");
+	let target_path: PathBuf = get_target_path(&parameters, "bmp");
+	
+	if overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+
+	let bmp_data: Vec<u8> = bmp_bytes(data, parameters.bmp_header, parameters.rle);
+	
+	// Write out
+	let bmp_file: File;
+	match File::create(&target_path) {
+		Ok(file) => bmp_file = file,
+		_ => {
+			println!("sprite_make::make_bmp() error: Could not create target file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+		
+	let mut buffer = BufWriter::new(bmp_file);
+	
+	let _ = buffer.write_all(&bmp_data);
+	
+	match buffer.flush() {
+		Ok(_) => (),
+		_ => {
+			println!("sprite_make::make_bmp() error: Could not write to target file");
+			println!("\tFile: {}", &target_path.display());
+		}
+	}
+}
+
+fn gif_frame(data: &SpriteData) -> gif::Frame<'static> {
+	let color_count: usize = 2usize.pow(data.bit_depth as u32);
+	let mut rgb_palette: Vec<u8> = Vec::with_capacity(color_count * 3);
+	let mut transparent: Option<u8> = None;
+	let mut transparent_colors: [bool; 256] = [false; 256];
+	
+	// Color pal, first fully transparent color becomes the GIF transparent index
+	if !data.palette.is_empty() {
+		for color in 0..color_count {
+			rgb_palette.push(data.palette[4 * color + 0]);
+			rgb_palette.push(data.palette[4 * color + 1]);
+			rgb_palette.push(data.palette[4 * color + 2]);
+			
+			if data.palette[4 * color + 3] == 0x00 {
+				transparent_colors[color] = true;
+				
+				if transparent.is_none() {
+					transparent = Some(color as u8);
+				}
+			}
+		}
+	}
+	
+	// Grayscale pal
+	else {
+		for color in 0..color_count {
+			rgb_palette.push(color as u8);
+			rgb_palette.push(color as u8);
+			rgb_palette.push(color as u8);
+		}
+	}
+	
+	// Indices past the palette are clamped, same as 4 bpp BIN output. GIFs only have one
+	// transparent index, so pixels using any other transparent color (such as +R's
+	// slots 32, 40, ...) are moved onto it.
+	let mut pixels: Vec<u8> = Vec::with_capacity(data.pixels.len());
+	for pixel in 0..data.pixels.len() {
+		let index: usize = std::cmp::min(data.pixels[pixel] as usize, color_count - 1);
+		
+		match transparent {
+			Some(transparent_index) if transparent_colors[index] => pixels.push(transparent_index),
+			_ => pixels.push(index as u8),
+		}
+	}
+	
+	let mut frame = gif::Frame::from_palette_pixels(data.width, data.height, pixels, rgb_palette, transparent);
+	frame.dispose = gif::DisposalMethod::Background;
+	return frame;
+}
+
+
+pub fn make_gif(parameters: Parameters, data: SpriteData) {
+	// Set target filename
+	let target_path: PathBuf = get_target_path(&parameters, "gif");
+	
+	if overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+	
+	write_gif(&target_path, vec![data], 0);
+}
+
+
+pub fn make_gif_animation(parameters: Parameters, frames: Vec<SpriteData>) {
+	let target_path: PathBuf = get_target_path(&parameters, "gif");
+	
+	if overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+	
+	// GIF delays are in hundredths of a second
+	write_gif(&target_path, frames, (parameters.frame_delay / 10) as u16);
+}
+
+
+fn write_gif(target_path: &PathBuf, frames: Vec<SpriteData>, delay: u16) {
+	let gif_file: File;
+	match File::create(&target_path) {
+		Ok(file) => gif_file = file,
+		_ => {
+			println!("sprite_make::write_gif() error: Could not create target GIF file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	// Frames are placed at the top left corner of the largest frame
+	let mut width: u16 = 0;
+	let mut height: u16 = 0;
+	for frame in 0..frames.len() {
+		width = std::cmp::max(width, frames[frame].width);
+		height = std::cmp::max(height, frames[frame].height);
+	}
+	
+	let buffer = BufWriter::new(gif_file);
+	let mut encoder: gif::Encoder<BufWriter<File>>;
+	match gif::Encoder::new(buffer, width, height, &[]) {
+		Ok(value) => encoder = value,
+		_ => {
+			println!("sprite_make::write_gif() error: Could not write GIF header");
+			println!("\tFile: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	if frames.len() > 1 {
+		let _ = encoder.set_repeat(gif::Repeat::Infinite);
+	}
+	
+	for frame in 0..frames.len() {
+		let mut gif_frame: gif::Frame = gif_frame(&frames[frame]);
+		gif_frame.delay = delay;
+		
+		match encoder.write_frame(&gif_frame) {
+			Ok(_) => (),
+			_ => {
+				println!("sprite_make::write_gif() error: Could not write GIF frame");
+				println!("\tFile: {}", &target_path.display());
+				return;
+			},
+		}
+	}
+}
+
+
+pub fn make_tga(parameters: Parameters, data: SpriteData) {
+	// Set target filename
+	let target_path: PathBuf = get_target_path(&parameters, "tga");
 	
 	if overwrite_blocked(&target_path, parameters.overwrite) {
 		return;
 	}
+	
+	let tga_data: Vec<u8> = tga_bytes(&data, parameters.rle);
+	
+	// Write out
+	let tga_file: File;
+	match File::create(&target_path) {
+		Ok(file) => tga_file = file,
+		_ => {
+			println!("sprite_make::make_tga() error: Could not create target file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	let mut buffer = BufWriter::new(tga_file);
+	let _ = buffer.write_all(&tga_data);
+	
+	match buffer.flush() {
+		Ok(_) => (),
+		_ => {
+			println!("sprite_make::make_tga() error: Could not write to target file");
+			println!("\tFile: {}", &target_path.display());
+		}
+	}
+}
+
+
+// Whole TGA file, color-mapped or RLE color-mapped
+fn tga_bytes(data: &SpriteData, rle: bool) -> Vec<u8> {
+	let color_count: usize = 2usize.pow(data.bit_depth as u32);
+	let mut tga_data: Vec<u8> = Vec::new();
+	
+	// Header
+	tga_data.push(0x00);	// ID length
+	tga_data.push(0x01);	// Color map present
+	
+	// Image type, color-mapped or RLE color-mapped
+	if rle {
+		tga_data.push(0x09);
+	}
+	else {
+		tga_data.push(0x01);
+	}
+	
+	// Color map specification, 32-bit entries starting at 0
+	tga_data.extend_from_slice(&0u16.to_le_bytes());
+	tga_data.extend_from_slice(&(color_count as u16).to_le_bytes());
+	tga_data.push(32);
+	
+	// Image specification
+	tga_data.extend_from_slice(&0u16.to_le_bytes());	// X origin
+	tga_data.extend_from_slice(&0u16.to_le_bytes());	// Y origin
+	tga_data.extend_from_slice(&data.width.to_le_bytes());
+	tga_data.extend_from_slice(&data.height.to_le_bytes());
+	tga_data.push(8);
+	
+	// Top-left origin, 8 alpha bits
+	tga_data.push(0x28);
+	
+	// Color map (BGRA)
+	for color in 0..color_count {
+		if data.palette.is_empty() {
+			tga_data.extend_from_slice(&[color as u8, color as u8, color as u8, 0xFF]);
+		}
+		
+		else {
+			tga_data.push(data.palette[4 * color + 2]);
+			tga_data.push(data.palette[4 * color + 1]);
+			tga_data.push(data.palette[4 * color + 0]);
+			tga_data.push(data.palette[4 * color + 3]);
+		}
+	}
+	
+	// Indices past the palette are clamped
+	let mut pixels: Vec<u8> = Vec::with_capacity(data.pixels.len());
+	for pixel in 0..data.pixels.len() {
+		pixels.push(std::cmp::min(data.pixels[pixel] as usize, color_count - 1) as u8);
+	}
+	
+	if rle {
+		// Packets don't cross rows
+		for y in 0..data.height as usize {
+			let row_start: usize = y * data.width as usize;
+			tga_data.extend_from_slice(&tga_rle_row(&pixels[row_start..row_start + data.width as usize]));
+		}
+	}
+	
+	else {
+		tga_data.extend_from_slice(&pixels);
+	}
+	
+	return tga_data;
+}
 
-	// BITMAPFILEHEADER, BITMAPCOREHEADER
-	let header: Vec<u8> = bmp_header(data.width, data.height, data.bit_depth);
+
+fn tga_rle_row(row: &[u8]) -> Vec<u8> {
+	let mut output: Vec<u8> = Vec::new();
+	let mut position: usize = 0;
+	
+	while position < row.len() {
+		// Length of the run starting here, up to 128
+		let mut run: usize = 1;
+		while position + run < row.len() && run < 128 && row[position + run] == row[position] {
+			run += 1;
+		}
+		
+		if run > 1 {
+			output.push(0x80 | (run - 1) as u8);
+			output.push(row[position]);
+			position += run;
+			continue;
+		}
+		
+		// Raw packet until the next run of at least two, up to 128
+		let start: usize = position;
+		while position < row.len() && position - start < 128 {
+			if position + 1 < row.len() && row[position + 1] == row[position] {
+				break;
+			}
+			
+			position += 1;
+		}
+		
+		output.push((position - start - 1) as u8);
+		output.extend_from_slice(&row[start..position]);
+	}
 	
+	return output;
+}
+
+
+// Whole BMP file, RLE only applies to 4 and 8 bpp
+fn bmp_bytes(data: SpriteData, bmp_header_type: BmpHeader, rle: bool) -> Vec<u8> {
 	// Color table
-	let mut color_table: Vec<u8> = Vec::with_capacity(768);
+	let mut color_table: Vec<u8> = Vec::with_capacity(1024);
 	let color_count: usize = 2usize.pow(data.bit_depth as u32);
 	
 	// Grayscale
@@ -361,66 +896,510 @@ pub fn make_bmp(parameters: Parameters, data: SpriteData) {
 			color_table.push(color as u8);
 			color_table.push(color as u8);
 			color_table.push(color as u8);
+			
+			if bmp_header_type != BmpHeader::CORE {
+				color_table.push(0x00);
+			}
 		}
 	}
 	
-	// Palette (no alpha)
+	// Palette, alpha goes in the RGBQUAD reserved byte for V4/V5 headers
 	else {
 		for color in 0..color_count {
 			color_table.push(data.palette[4 * color + 2]);
 			color_table.push(data.palette[4 * color + 1]);
 			color_table.push(data.palette[4 * color + 0]);
+			
+			match bmp_header_type {
+				BmpHeader::CORE => (),
+				BmpHeader::INFO => color_table.push(0x00),
+				BmpHeader::V4 | BmpHeader::V5 => color_table.push(data.palette[4 * color + 3]),
+			}
 		}
 	}
 	
-	// Write out
-	let bmp_file: File;
-	match File::create(&target_path) {
-		Ok(file) => bmp_file = file,
-		_ => {
-			println!("sprite_make::make_bmp() error: Could not create target file");
-			println!("\tSkipped: {}", &target_path.display());
-			return;
-		},
+	// Pixel array
+	let mut byte_vector: Vec<u8>;
+	let mut pixel_array: Vec<u8> = Vec::new();
+	let compression: u32;
+	
+	// BI_RLE8 (1) or BI_RLE4 (2), rows are encoded upside-down without padding.
+	// There's no RLE for 1 and 2 bpp, those are always written as BI_RGB
+	if rle && data.bit_depth >= 4 {
+		let width: usize = data.width as usize;
+		
+		for y in (0..data.height as usize).rev() {
+			pixel_array.extend_from_slice(&bmp_rle_row(&data.pixels[y * width..(y + 1) * width], data.bit_depth == 4));
+			
+			// End of line, or end of bitmap after the last row
+			if y > 0 {
+				pixel_array.extend_from_slice(&[0x00, 0x00]);
+			}
+			else {
+				pixel_array.extend_from_slice(&[0x00, 0x01]);
+			}
+		}
+		
+		match data.bit_depth {
+			4 => compression = 2,
+			8 => compression = 1,
+			// Shouldn't happen
+			_ => panic!("sprite_make::make_bmp() error: Invalid bit depth"),
+		}
 	}
+	
+	// BI_RGB
+	else {
+		match data.bit_depth {
+			1 => {
+				byte_vector = sprite_transform::align_to_byte(data.pixels, data.height as usize, 1);
+				byte_vector = sprite_transform::bpp_to_1(byte_vector, false);
+			},
+			2 => {
+				byte_vector = sprite_transform::align_to_byte(data.pixels, data.height as usize, 2);
+				byte_vector = sprite_transform::bpp_to_2(byte_vector, false);
+			},
+			4 => {
+				byte_vector = sprite_transform::align_to_byte(data.pixels, data.height as usize, 4);
+				byte_vector = sprite_transform::bpp_to_4(byte_vector, false);
+			},
+			8 => byte_vector = data.pixels,
+			// Shouldn't happen
+			_ => panic!("sprite_make::make_bmp() error: Invalid bit depth"),
+		}
 		
-	let mut buffer = BufWriter::new(bmp_file);
+		// Cheers Wikipedia
+		let row_length: usize = (((data.bit_depth * data.width + 31) / 32) * 4) as usize;
+		let byte_width: usize = byte_vector.len() / data.height as usize;
+		let padding: usize = row_length - byte_width;
+		
+		// Upside-down write with padding
+		for y in (0..data.height as usize).rev() {
+			let row_start: usize = y * byte_width;
+			pixel_array.extend_from_slice(&byte_vector[row_start..row_start + byte_width]);
+			pixel_array.extend_from_slice(&vec![0u8; padding]);
+		}
+		
+		compression = 0;
+	}
 	
-	let _ = buffer.write_all(&header);
-	let _ = buffer.write_all(&color_table);
+	// BITMAPFILEHEADER, DIBHEADER
+	let header: Vec<u8> = bmp_header(data.width, data.height, data.bit_depth, bmp_header_type, compression, pixel_array.len() as u32, !data.palette.is_empty());
 	
-	let mut byte_vector: Vec<u8>;
+	let mut bmp_data: Vec<u8> = header;
+	bmp_data.extend_from_slice(&color_table);
+	bmp_data.extend_from_slice(&pixel_array);
+	return bmp_data;
+}
+
+
+fn bmp_rle_row(row: &[u8], four_bit: bool) -> Vec<u8> {
+	let mut output: Vec<u8> = Vec::new();
+	let mut position: usize = 0;
+	
+	while position < row.len() {
+		// Length of the run starting here, up to 255
+		let mut run: usize = 1;
+		while position + run < row.len() && run < 255 && row[position + run] == row[position] {
+			run += 1;
+		}
+		
+		if run > 1 {
+			output.push(run as u8);
+			
+			if four_bit {
+				output.push(std::cmp::min(row[position], 0xF) * 0x11);
+			}
+			else {
+				output.push(row[position]);
+			}
+			
+			position += run;
+			continue;
+		}
+		
+		// Absolute mode until the next run of at least two, up to 255
+		let start: usize = position;
+		while position < row.len() && position - start < 255 {
+			if position + 1 < row.len() && row[position + 1] == row[position] {
+				break;
+			}
+			
+			position += 1;
+		}
+		
+		let length: usize = position - start;
+		
+		// Absolute mode lengths below 3 are escape codes, write single pixel runs instead
+		if length < 3 {
+			for pixel in start..position {
+				output.push(0x01);
+				
+				if four_bit {
+					output.push(std::cmp::min(row[pixel], 0xF) << 4);
+				}
+				else {
+					output.push(row[pixel]);
+				}
+			}
+			
+			continue;
+		}
+		
+		output.push(0x00);
+		output.push(length as u8);
+		
+		let absolute: Vec<u8>;
+		if four_bit {
+			absolute = sprite_transform::bpp_to_4(row[start..position].to_vec(), false);
+		}
+		else {
+			absolute = row[start..position].to_vec();
+		}
+		
+		output.extend_from_slice(&absolute);
+		
+		// Absolute runs are padded to a 16-bit boundary
+		if absolute.len() % 2 != 0 {
+			output.push(0x00);
+		}
+	}
+	
+	return output;
+}
+
+
+pub fn make_pcx(parameters: Parameters, data: SpriteData) {
+	// Set target filename
+	let target_path: PathBuf = get_target_path(&parameters, "pcx");
+	
+	if overwrite_blocked(&target_path, parameters.overwrite) {
+		return;
+	}
+	
+	let color_count: usize = 2usize.pow(data.bit_depth as u32);
+	let width: usize = data.width as usize;
+	
+	// Palette, grayscale if none
+	let mut rgb_palette: Vec<u8> = Vec::with_capacity(color_count * 3);
+	for color in 0..color_count {
+		if data.palette.is_empty() {
+			rgb_palette.extend_from_slice(&[color as u8, color as u8, color as u8]);
+		}
+		
+		else {
+			rgb_palette.extend_from_slice(&data.palette[4 * color..4 * color + 3]);
+		}
+	}
+	
+	// 8, 2 and 1 bpp are one chunky plane, 4 bpp is four 1-bit planes
+	let bits_per_plane: u8;
+	let planes: u8;
+	let bytes_per_line: usize;
 	
 	match data.bit_depth {
-		// 1 and 2 bpp not currently in use
-		// 1 => byte_vector = sprite_transform::bpp_to_1(data.pixels, false),
-		// 2 => byte_vector = sprite_transform::bpp_to_2(data.pixels, false),
+		1 => {
+			bits_per_plane = 1;
+			planes = 1;
+			bytes_per_line = (((width + 7) / 8) + 1) / 2 * 2;
+		},
+		
+		2 => {
+			bits_per_plane = 2;
+			planes = 1;
+			bytes_per_line = (((width + 3) / 4) + 1) / 2 * 2;
+		},
+		
 		4 => {
-			byte_vector = sprite_transform::align_to_4(data.pixels, data.height as usize);
-			byte_vector = sprite_transform::bpp_to_4(byte_vector, false);
+			bits_per_plane = 1;
+			planes = 4;
+			bytes_per_line = (((width + 7) / 8) + 1) / 2 * 2;
+		},
+		
+		8 => {
+			bits_per_plane = 8;
+			planes = 1;
+			bytes_per_line = (width + 1) / 2 * 2;
 		},
-		8 => byte_vector = data.pixels,
+		
 		// Shouldn't happen
-		_ => panic!("sprite_make::make_bmp() error: Invalid bit depth"),
+		_ => panic!("sprite_make::make_pcx() error: Invalid bit depth"),
 	}
 	
-	// Cheers Wikipedia
-	let row_length: usize = (((data.bit_depth * data.width + 31) / 32) * 4) as usize;
-	let byte_width: usize = byte_vector.len() / data.height as usize;
-	let padding: usize = row_length - byte_width;
+	let mut pcx_data: Vec<u8> = Vec::with_capacity(PCX_HEADER_SIZE);
+	
+	// Header
+	pcx_data.push(0x0A);	// Manufacturer
+	pcx_data.push(0x05);	// Version 3.0
+	pcx_data.push(0x01);	// RLE
+	pcx_data.push(bits_per_plane);
+	pcx_data.extend_from_slice(&0u16.to_le_bytes());	// X min
+	pcx_data.extend_from_slice(&0u16.to_le_bytes());	// Y min
+	pcx_data.extend_from_slice(&(data.width - 1).to_le_bytes());
+	pcx_data.extend_from_slice(&(data.height - 1).to_le_bytes());
+	pcx_data.extend_from_slice(&72u16.to_le_bytes());	// Horizontal DPI
+	pcx_data.extend_from_slice(&72u16.to_le_bytes());	// Vertical DPI
 	
-	// Upside-down write with padding
-	for y in (0..data.height as usize).rev() {
-		let row_start: usize = y * byte_width;
-		let _ = buffer.write_all(&byte_vector[row_start..row_start + byte_width]);
-		let _ = buffer.write_all(&vec![0u8; padding]);
+	// 16-color header palette
+	let mut header_palette: Vec<u8> = vec![0; 48];
+	let header_colors: usize = std::cmp::min(color_count, 16);
+	header_palette[..header_colors * 3].copy_from_slice(&rgb_palette[..header_colors * 3]);
+	pcx_data.extend_from_slice(&header_palette);
+	
+	pcx_data.push(0x00);	// Reserved
+	pcx_data.push(planes);
+	pcx_data.extend_from_slice(&(bytes_per_line as u16).to_le_bytes());
+	pcx_data.extend_from_slice(&1u16.to_le_bytes());	// Color palette
+	pcx_data.resize(PCX_HEADER_SIZE, 0u8);
+	
+	// Indices past the palette are clamped
+	let mut pixels: Vec<u8> = Vec::with_capacity(data.pixels.len());
+	for pixel in 0..data.pixels.len() {
+		pixels.push(std::cmp::min(data.pixels[pixel] as usize, color_count - 1) as u8);
+	}
+	
+	// Scanlines, RLE per scanline
+	for y in 0..data.height as usize {
+		let row: &[u8] = &pixels[y * width..(y + 1) * width];
+		let mut scanline: Vec<u8> = Vec::with_capacity(planes as usize * bytes_per_line);
+		
+		match data.bit_depth {
+			1 => {
+				let mut row_bits: Vec<u8> = row.to_vec();
+				row_bits.resize(bytes_per_line * 8, 0u8);
+				scanline.extend_from_slice(&sprite_transform::bpp_to_1(row_bits, false));
+			},
+			
+			2 => {
+				let mut row_bits: Vec<u8> = row.to_vec();
+				row_bits.resize(bytes_per_line * 4, 0u8);
+				scanline.extend_from_slice(&sprite_transform::bpp_to_2(row_bits, false));
+			},
+			
+			4 => {
+				for plane in 0..4 {
+					let mut plane_bits: Vec<u8> = row.iter().map(|pixel| (pixel >> plane) & 0x1).collect();
+					plane_bits.resize(bytes_per_line * 8, 0u8);
+					scanline.extend_from_slice(&sprite_transform::bpp_to_1(plane_bits, false));
+				}
+			},
+			
+			_ => {
+				scanline.extend_from_slice(row);
+				scanline.resize(bytes_per_line, 0u8);
+			},
+		}
+		
+		let mut position: usize = 0;
+		while position < scanline.len() {
+			let mut run: usize = 1;
+			while position + run < scanline.len() && run < 63 && scanline[position + run] == scanline[position] {
+				run += 1;
+			}
+			
+			// Single bytes below 0xC0 don't need a run
+			if run > 1 || scanline[position] >= 0xC0 {
+				pcx_data.push(0xC0 | run as u8);
+			}
+			
+			pcx_data.push(scanline[position]);
+			position += run;
+		}
 	}
 	
+	// 256-color palette
+	if data.bit_depth == 8 {
+		pcx_data.push(0x0C);
+		pcx_data.extend_from_slice(&rgb_palette);
+	}
+	
+	// Write out
+	let pcx_file: File;
+	match File::create(&target_path) {
+		Ok(file) => pcx_file = file,
+		_ => {
+			println!("sprite_make::make_pcx() error: Could not create target file");
+			println!("\tSkipped: {}", &target_path.display());
+			return;
+		},
+	}
+	
+	let mut buffer = BufWriter::new(pcx_file);
+	let _ = buffer.write_all(&pcx_data);
+	
 	match buffer.flush() {
 		Ok(_) => (),
 		_ => {
-			println!("sprite_make::make_bmp() error: Could not write to target file");
+			println!("sprite_make::make_pcx() error: Could not write to target file");
 			println!("\tFile: {}", &target_path.display());
 		}
 	}
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		sprite_get,
+		test_file::TempFile,
+	};
+	
+	#[test]
+	fn gif_frame_merges_transparent_colors() {
+		let mut palette: Vec<u8> = vec![0x10; 4 * 256];
+		for color in 0..256 {
+			palette[4 * color + 3] = 0x80;
+		}
+		palette[3] = 0x00;
+		palette[4 * 32 + 3] = 0x00;
+		palette[4 * 40 + 3] = 0x00;
+		
+		let data: SpriteData = SpriteData {
+			width: 5,
+			height: 1,
+			bit_depth: 8,
+			pixels: vec![0, 1, 32, 40, 41],
+			palette: palette,
+			..SpriteData::default()
+		};
+		
+		let frame: gif::Frame = gif_frame(&data);
+		
+		assert_eq!(frame.transparent, Some(0));
+		assert_eq!(frame.buffer.to_vec(), vec![0, 1, 0, 0, 41]);
+	}
+	
+	#[test]
+	fn stem_with_suffix_appends() {
+		let name: OsString = stem_with_suffix(&PathBuf::from("dir/sprite.bin"), "_pal03.png");
+		assert_eq!(name, OsString::from("sprite_pal03.png"));
+	}
+	
+	#[cfg(unix)]
+	#[test]
+	fn stem_with_suffix_keeps_non_utf8_names() {
+		use std::os::unix::ffi::{OsStrExt, OsStringExt};
+		
+		let source_path: PathBuf = PathBuf::from(std::ffi::OsStr::from_bytes(b"dir/n\xFFx.bin"));
+		let name: OsString = stem_with_suffix(&source_path, "_pal00.png");
+		
+		assert_eq!(name.into_vec(), b"n\xFFx_pal00.png".to_vec());
+	}
+	
+	// Two rows of runs 1, 2, 3 and run_length pixels long, then a raw stretch of
+	// raw_length pixels. The second row is the first reversed.
+	fn rle_sprite(bit_depth: u16, run_length: usize, raw_length: usize) -> SpriteData {
+		let color_count: usize = 2usize.pow(bit_depth as u32);
+		let mut row: Vec<u8> = vec![5, 6, 6, 7, 7, 7];
+		row.resize(row.len() + run_length, 8);
+		
+		for pixel in 0..raw_length {
+			row.push((pixel % (color_count - 1)) as u8 + 1);
+		}
+		
+		let mut pixels: Vec<u8> = row.clone();
+		row.reverse();
+		pixels.extend_from_slice(&row);
+		
+		return SpriteData {
+			width: row.len() as u16,
+			height: 2,
+			bit_depth: bit_depth,
+			pixels: pixels,
+			palette: (0..color_count * 4).map(|byte| byte as u8).collect(),
+			..SpriteData::default()
+		};
+	}
+	
+	#[test]
+	fn tga_rle_round_trip() {
+		// Runs and raw packets both split after 128 pixels
+		let data: SpriteData = rle_sprite(8, 129, 130);
+		let tga_data: Vec<u8> = tga_bytes(&data, true);
+		
+		assert_eq!(tga_data[0x02], 9);
+		
+		let read_data: SpriteData = sprite_get::get_tga(&TempFile::new("rle.tga", &tga_data));
+		
+		assert_eq!((read_data.width, read_data.height), (data.width, data.height));
+		assert_eq!(read_data.pixels, data.pixels);
+		assert_eq!(read_data.palette, data.palette);
+	}
+	
+	#[test]
+	fn bmp_rle8_round_trip() {
+		// Runs split after 255 pixels, and raw stretches of 1 and 2 pixels (written as
+		// runs since they'd be escape codes) as well as odd and even absolute runs
+		for (run_length, raw_length) in [(256, 1), (300, 2), (9, 3), (4, 4)] {
+			let data: SpriteData = rle_sprite(8, run_length, raw_length);
+			let pixels: Vec<u8> = data.pixels.clone();
+			let bmp_data: Vec<u8> = bmp_bytes(data, BmpHeader::INFO, true);
+			
+			assert_eq!(bmp_data[30], 1);
+			
+			let read_data: SpriteData = sprite_get::get_bmp(&TempFile::new("rle8.bmp", &bmp_data));
+			assert_eq!(read_data.pixels, pixels, "run {} raw {}", run_length, raw_length);
+		}
+	}
+	
+	#[test]
+	fn bmp_rle4_round_trip() {
+		// Absolute runs pack two pixels a byte, odd lengths leave half a byte over
+		for (run_length, raw_length) in [(256, 1), (300, 2), (9, 3), (4, 4), (1, 5), (2, 6), (3, 17)] {
+			let data: SpriteData = rle_sprite(4, run_length, raw_length);
+			let pixels: Vec<u8> = data.pixels.clone();
+			let bmp_data: Vec<u8> = bmp_bytes(data, BmpHeader::INFO, true);
+			
+			assert_eq!(bmp_data[30], 2);
+			
+			let read_data: SpriteData = sprite_get::get_bmp(&TempFile::new("rle4.bmp", &bmp_data));
+			assert_eq!(read_data.pixels, pixels, "run {} raw {}", run_length, raw_length);
+		}
+	}
+	
+	#[test]
+	fn bmp_v4_alpha_round_trip() {
+		let palette: Vec<u8> = vec![
+			10, 20, 30, 0x00,
+			40, 50, 60, 0x40,
+			70, 80, 90, 0x00,
+			100, 110, 120, 0x80,
+		];
+		
+		for bmp_header_type in [BmpHeader::V4, BmpHeader::V5] {
+			let data: SpriteData = SpriteData {
+				width: 4,
+				height: 1,
+				bit_depth: 2,
+				pixels: vec![0, 1, 2, 3],
+				palette: palette.clone(),
+				..SpriteData::default()
+			};
+			
+			let bmp_data: Vec<u8> = bmp_bytes(data, bmp_header_type, false);
+			assert_eq!(&bmp_data[66..70], &0xFF000000u32.to_le_bytes());
+			
+			let read_data: SpriteData = sprite_get::get_bmp(&TempFile::new("alpha.bmp", &bmp_data));
+			assert_eq!(read_data.palette, palette);
+			assert!(!read_data.metadata.alpha_filled);
+		}
+	}
+	
+	#[test]
+	fn bmp_v4_without_palette_has_no_alpha() {
+		let data: SpriteData = SpriteData {
+			width: 2,
+			height: 1,
+			bit_depth: 1,
+			pixels: vec![0, 1],
+			..SpriteData::default()
+		};
+		
+		let bmp_data: Vec<u8> = bmp_bytes(data, BmpHeader::V4, false);
+		assert_eq!(&bmp_data[66..70], &[0, 0, 0, 0]);
+		
+		let read_data: SpriteData = sprite_get::get_bmp(&TempFile::new("no_alpha.bmp", &bmp_data));
+		assert!(read_data.metadata.alpha_filled);
+	}
+}
@@ -1,4 +1,5 @@
 use std::cmp;
+use std::collections::HashMap;
 
 
 pub fn transform_index(mut value: u8) -> u8 {
@@ -19,6 +20,33 @@ pub fn transform_index(mut value: u8) -> u8 {
 }
 
 
+pub fn reindex_palette(input_palette: &Vec<u8>) -> Vec<u8> {
+	// Move every color to the slot its pixels are reindexed to
+	let mut output_palette: Vec<u8> = input_palette.clone();
+	let color_count: usize = input_palette.len() / 4;
+	
+	for index in 0..color_count {
+		let target: usize = transform_index(index as u8) as usize;
+		
+		if target >= color_count {
+			continue;
+		}
+		
+		output_palette[4 * target..4 * target + 4].copy_from_slice(&input_palette[4 * index..4 * index + 4]);
+	}
+	
+	return output_palette;
+}
+
+
+// PS2 CSM1 stores 256-color CLUTs with entries 8-15 and 16-23 swapped in every
+// block of 32, the same swap transform_index() does on pixels. Swizzling a palette
+// twice gives back the original, so this both swizzles and unswizzles.
+pub fn swizzle_palette(input_palette: &Vec<u8>) -> Vec<u8> {
+	return reindex_palette(input_palette);
+}
+
+
 pub fn indexed_as_rgb(input_pixels: Vec<u8>, palette: &Vec<u8>) -> Vec<u8> {
 	let mut output_pixels: Vec<u8> = Vec::new();
 	
@@ -30,30 +58,170 @@ pub fn indexed_as_rgb(input_pixels: Vec<u8>, palette: &Vec<u8>) -> Vec<u8> {
 }
 
 
-// Works, but not currently used.
-// pub fn bpp_to_1(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
-	// let mut output_pixels: Vec<u8> = Vec::new();
-	// let mut index: usize = 0;
+pub fn remap_indices(input_pixels: Vec<u8>, remap_table: &Vec<u8>) -> Vec<u8> {
+	let mut output_pixels: Vec<u8> = Vec::with_capacity(input_pixels.len());
+	
+	for pixel in 0..input_pixels.len() {
+		output_pixels.push(remap_table[input_pixels[pixel] as usize]);
+	}
+	
+	return output_pixels;
+}
+
+
+pub fn remap_palette(input_palette: &Vec<u8>, remap_table: &Vec<u8>) -> Vec<u8> {
+	// Colors follow their pixels. Slots that map onto themselves keep their color,
+	// otherwise the first slot mapped onto a target wins.
+	let mut output_palette: Vec<u8> = input_palette.clone();
+	let color_count: usize = input_palette.len() / 4;
+	let mut written: [bool; 256] = [false; 256];
+	
+	for index in 0..color_count {
+		if remap_table[index] as usize == index {
+			written[index] = true;
+		}
+	}
+	
+	for index in 0..color_count {
+		let target: usize = remap_table[index] as usize;
+		
+		if written[target] || target >= color_count {
+			continue;
+		}
+		
+		output_palette[4 * target..4 * target + 4].copy_from_slice(&input_palette[4 * index..4 * index + 4]);
+		written[target] = true;
+	}
+	
+	return output_palette;
+}
+
+
+pub fn get_used_indices(input_pixels: &Vec<u8>) -> Vec<u8> {
+	let mut used: [bool; 256] = [false; 256];
+	let mut output_indices: Vec<u8> = Vec::new();
+	
+	for pixel in 0..input_pixels.len() {
+		used[input_pixels[pixel] as usize] = true;
+	}
+	
+	for index in 0..256 {
+		if used[index] {
+			output_indices.push(index as u8);
+		}
+	}
+	
+	return output_indices;
+}
+
+
+// PS2 alpha runs from 0x00 to 0x80 (opaque), PC alpha from 0x00 to 0xFF.
+// Values above 0x80 are treated as opaque.
+pub fn alpha_to_pc(input_palette: &Vec<u8>) -> Vec<u8> {
+	let mut output_palette: Vec<u8> = input_palette.clone();
+	
+	for color in 0..input_palette.len() / 4 {
+		let alpha: usize = cmp::min(input_palette[4 * color + 3], 0x80) as usize;
+		output_palette[4 * color + 3] = ((alpha * 0xFF + 0x40) / 0x80) as u8;
+	}
+	
+	return output_palette;
+}
+
+
+pub fn alpha_to_ps2(input_palette: &Vec<u8>) -> Vec<u8> {
+	let mut output_palette: Vec<u8> = input_palette.clone();
+	
+	for color in 0..input_palette.len() / 4 {
+		let alpha: usize = input_palette[4 * color + 3] as usize;
+		output_palette[4 * color + 3] = ((alpha * 0x80 + 0x7F) / 0xFF) as u8;
+	}
+	
+	return output_palette;
+}
+
+
+// Used indices in the order they get packed, index 0 is always kept in place as it
+// holds transparency
+pub fn get_compact_indices(input_pixels: &Vec<u8>) -> Vec<u8> {
+	let mut used_indices: Vec<u8> = get_used_indices(input_pixels);
+	
+	if used_indices.first() != Some(&0) {
+		used_indices.insert(0, 0);
+	}
+	
+	return used_indices;
+}
+
+
+// Used indices that don't fit in color_count slots
+pub fn get_colliding_indices(used_indices: &Vec<u8>, color_count: usize) -> Vec<u8> {
+	if used_indices.len() <= color_count {
+		return Vec::new();
+	}
+	
+	return used_indices[color_count..].to_vec();
+}
+
+
+pub fn compact_indices(input_pixels: Vec<u8>, used_indices: &Vec<u8>) -> Vec<u8> {
+	// Map every used index to its position in the (sorted) list of used indices
+	let mut index_map: [u8; 256] = [0; 256];
+	
+	for index in 0..used_indices.len() {
+		index_map[used_indices[index] as usize] = index as u8;
+	}
+	
+	let mut output_pixels: Vec<u8> = Vec::with_capacity(input_pixels.len());
+	
+	for pixel in 0..input_pixels.len() {
+		output_pixels.push(index_map[input_pixels[pixel] as usize]);
+	}
+	
+	return output_pixels;
+}
+
+
+pub fn compact_palette(input_palette: &Vec<u8>, used_indices: &Vec<u8>, color_count: usize) -> Vec<u8> {
+	let mut output_palette: Vec<u8> = vec![0; color_count * 4];
+	
+	for index in 0..cmp::min(used_indices.len(), color_count) {
+		let source: usize = 4 * used_indices[index] as usize;
+		
+		if source + 4 > input_palette.len() {
+			continue;
+		}
+		
+		output_palette[4 * index..4 * index + 4].copy_from_slice(&input_palette[source..source + 4]);
+	}
+	
+	return output_palette;
+}
+
+
+pub fn bpp_to_1(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
+	let mut output_pixels: Vec<u8> = Vec::new();
+	let mut index: usize = 0;
 	
-	// while index < input_pixels.len() {
-		// let process_count: usize = min(8, input_pixels.len() - index);
-		// let mut byte: u8 = 0;
+	while index < input_pixels.len() {
+		let process_count: usize = cmp::min(8, input_pixels.len() - index);
+		let mut byte: u8 = 0;
 		
-		// for pixel in 0..process_count {
-			// let bit: u8 = (input_pixels[index + pixel] > 0) as u8;
-			// byte = byte | bit << (7 - pixel);
-		// }
+		for pixel in 0..process_count {
+			let bit: u8 = (input_pixels[index + pixel] > 0) as u8;
+			byte = byte | bit << (7 - pixel);
+		}
 		
-		// if flip {
-			// byte = byte.reverse_bits();
-		// }
+		if flip {
+			byte = byte.reverse_bits();
+		}
 		
-		// output_pixels.push(byte);
-		// index += 8;
-	// }
+		output_pixels.push(byte);
+		index += 8;
+	}
 	
-	// return output_pixels;
-// }
+	return output_pixels;
+}
 
 
 pub fn bpp_from_1(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
@@ -75,34 +243,32 @@ pub fn bpp_from_1(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
 }
 
 
-// Works, but not currently used.
-// pub fn bpp_to_2(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
-	// let mut output_pixels: Vec<u8> = Vec::new();
-	// let mut index: usize = 0;
+pub fn bpp_to_2(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
+	let mut output_pixels: Vec<u8> = Vec::new();
+	let mut index: usize = 0;
 	
-	// while index < input_pixels.len() {
-		// let process_count: usize = min(4, input_pixels.len() - index);
-		// let mut byte_pixels: Vec<u8> = Vec::new();
-		// let mut byte: u8 = 0;
-		
-		// for pixel in 0..process_count {
-			// byte_pixels.push(input_pixels[index + pixel]);
-		// }
-		
-		// if !flip {
-			// byte_pixels.reverse();
-		// }
+	// Four pixels per byte, the first pixel in the high bits (or low bits when flipped)
+	while index < input_pixels.len() {
+		let process_count: usize = cmp::min(4, input_pixels.len() - index);
+		let mut byte: u8 = 0;
 		
-		// for pixel in 0..byte_pixels.len() {
-			// byte = byte | byte_pixels[pixel] << 2 * pixel;
-		// }
+		for pixel in 0..process_count {
+			let value: u8 = cmp::min(input_pixels[index + pixel], 0x3);
+			
+			if flip {
+				byte = byte | value << (2 * pixel);
+			}
+			else {
+				byte = byte | value << (6 - 2 * pixel);
+			}
+		}
 		
-		// output_pixels.push(byte);
-		// index += 4;
-	// }
+		output_pixels.push(byte);
+		index += 4;
+	}
 	
-	// return output_pixels;
-// }
+	return output_pixels;
+}
 
 
 pub fn bpp_from_2(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
@@ -127,7 +293,7 @@ pub fn bpp_from_2(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
 	
 	return output_pixels;
 }
-	
+
 
 pub fn bpp_to_4(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
 	let mut output_pixels: Vec<u8> = Vec::new();
@@ -183,11 +349,13 @@ pub fn bpp_from_4(input_pixels: Vec<u8>, flip: bool) -> Vec<u8> {
 }
 
 
-pub fn align_to_4(input_pixels: Vec<u8>, height: usize) -> Vec<u8> {
+// Pads each row to a whole number of bytes once packed to bit_depth
+pub fn align_to_byte(input_pixels: Vec<u8>, height: usize, bit_depth: u16) -> Vec<u8> {
 	let mut output_pixels: Vec<u8> = Vec::new();
 	
 	let width: usize = input_pixels.len() / height;
-	let padding: usize = width % 2;
+	let pixels_per_byte: usize = 8 / bit_depth as usize;
+	let padding: usize = (pixels_per_byte - width % pixels_per_byte) % pixels_per_byte;
 	
 	for y in 0..height {
 		for x in 0..width {
@@ -214,4 +382,187 @@ pub fn trim_padding(input_pixels: Vec<u8>, width: usize, height: usize) -> Vec<u
 	}
 	
 	return output_pixels;
-}
\ No newline at end of file
+}
+
+
+// RGBA pixels to indices into a 256-color palette of their distinct colors, in the
+// order they first show up. Fully transparent pixels all become index 0. None if
+// there are more than 256 colors.
+pub fn direct_to_indexed(input_colors: &Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
+	let pixel_count: usize = input_colors.len() / 4;
+	let mut output_pixels: Vec<u8> = Vec::with_capacity(pixel_count);
+	let mut output_palette: Vec<u8> = Vec::with_capacity(256 * 4);
+	let mut color_map: HashMap<[u8; 4], u8> = HashMap::new();
+	
+	for pixel in 0..pixel_count {
+		if input_colors[4 * pixel + 3] == 0 {
+			output_palette.extend_from_slice(&[0, 0, 0, 0]);
+			break;
+		}
+	}
+	
+	for pixel in 0..pixel_count {
+		let color: [u8; 4] = input_colors[4 * pixel..4 * pixel + 4].try_into().unwrap();
+		
+		if color[3] == 0 {
+			output_pixels.push(0);
+			continue;
+		}
+		
+		match color_map.get(&color) {
+			Some(index) => output_pixels.push(*index),
+			
+			None => {
+				let index: usize = output_palette.len() / 4;
+				if index > 255 {
+					return None;
+				}
+				
+				color_map.insert(color, index as u8);
+				output_palette.extend_from_slice(&color);
+				output_pixels.push(index as u8);
+			},
+		}
+	}
+	
+	output_palette.resize(256 * 4, 0);
+	return Some((output_pixels, output_palette));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn compact_indices_keep_index_0() {
+		let pixels: Vec<u8> = vec![200, 17, 17, 3, 200];
+		let used_indices: Vec<u8> = get_compact_indices(&pixels);
+		
+		assert_eq!(used_indices, vec![0, 3, 17, 200]);
+		assert_eq!(compact_indices(pixels, &used_indices), vec![3, 2, 2, 1, 3]);
+	}
+	
+	#[test]
+	fn compact_indices_already_using_0() {
+		let pixels: Vec<u8> = vec![0, 255, 0, 128];
+		let used_indices: Vec<u8> = get_compact_indices(&pixels);
+		
+		assert_eq!(used_indices, vec![0, 128, 255]);
+		assert_eq!(compact_indices(pixels, &used_indices), vec![0, 2, 0, 1]);
+	}
+	
+	#[test]
+	fn compact_palette_moves_colors() {
+		let mut palette: Vec<u8> = vec![0; 4 * 256];
+		for color in 0..256 {
+			palette[4 * color..4 * color + 4].copy_from_slice(&[color as u8, 1, 2, 0x80]);
+		}
+		
+		let compacted: Vec<u8> = compact_palette(&palette, &vec![0, 3, 17, 200], 16);
+		
+		assert_eq!(compacted.len(), 4 * 16);
+		assert_eq!(&compacted[0..16], &[0, 1, 2, 0x80, 3, 1, 2, 0x80, 17, 1, 2, 0x80, 200, 1, 2, 0x80]);
+		assert!(compacted[16..].iter().all(|byte| *byte == 0));
+	}
+	
+	#[test]
+	fn compact_palette_short_palette() {
+		// Indices past the end of the palette are left black
+		let palette: Vec<u8> = vec![9, 9, 9, 9, 8, 8, 8, 8];
+		let compacted: Vec<u8> = compact_palette(&palette, &vec![0, 1, 40], 16);
+		
+		assert_eq!(&compacted[0..12], &[9, 9, 9, 9, 8, 8, 8, 8, 0, 0, 0, 0]);
+	}
+	
+	#[test]
+	fn colliding_indices_reported() {
+		let pixels: Vec<u8> = (1..=20).collect();
+		let used_indices: Vec<u8> = get_compact_indices(&pixels);
+		
+		// Index 0 is added, so 21 colors and the last 5 don't fit
+		assert_eq!(used_indices.len(), 21);
+		assert_eq!(get_colliding_indices(&used_indices, 16), vec![16, 17, 18, 19, 20]);
+	}
+	
+	#[test]
+	fn colliding_indices_none_at_16() {
+		let pixels: Vec<u8> = (0..16).map(|index| index * 10).collect();
+		let used_indices: Vec<u8> = get_compact_indices(&pixels);
+		
+		assert_eq!(used_indices.len(), 16);
+		assert!(get_colliding_indices(&used_indices, 16).is_empty());
+	}
+	
+	#[test]
+	fn direct_to_indexed_builds_palette() {
+		let colors: Vec<u8> = vec![
+			10, 20, 30, 0x80,
+			0, 0, 0, 0,
+			40, 50, 60, 0x80,
+			10, 20, 30, 0x80,
+			99, 99, 99, 0,
+		];
+		
+		let (pixels, palette) = direct_to_indexed(&colors).unwrap();
+		
+		assert_eq!(pixels, vec![1, 0, 2, 1, 0]);
+		assert_eq!(palette.len(), 256 * 4);
+		assert_eq!(&palette[..12], &[0, 0, 0, 0, 10, 20, 30, 0x80, 40, 50, 60, 0x80]);
+	}
+	
+	#[test]
+	fn direct_to_indexed_color_limit() {
+		let mut colors: Vec<u8> = Vec::new();
+		for color in 0..256u32 {
+			colors.extend_from_slice(&[color as u8, 0, 0, 0x80]);
+		}
+		
+		// 256 opaque colors fit, one more (or a transparent pixel taking index 0) doesn't
+		assert!(direct_to_indexed(&colors).is_some());
+		
+		let mut transparent: Vec<u8> = colors.clone();
+		transparent.extend_from_slice(&[0, 0, 0, 0]);
+		assert!(direct_to_indexed(&transparent).is_none());
+		
+		colors.extend_from_slice(&[0, 1, 0, 0x80]);
+		assert!(direct_to_indexed(&colors).is_none());
+	}
+	
+	#[test]
+	fn reindex_palette_follows_pixels() {
+		let palette: Vec<u8> = (0..256 * 4).map(|byte| (byte / 4) as u8).collect();
+		let pixels: Vec<u8> = (0..=255).collect();
+		
+		let reindexed_palette: Vec<u8> = reindex_palette(&palette);
+		let reindexed_pixels: Vec<u8> = pixels.iter().map(|pixel| transform_index(*pixel)).collect();
+		
+		// Every pixel still points at the color it had before
+		for pixel in 0..pixels.len() {
+			let before: usize = 4 * pixels[pixel] as usize;
+			let after: usize = 4 * reindexed_pixels[pixel] as usize;
+			assert_eq!(&reindexed_palette[after..after + 4], &palette[before..before + 4]);
+		}
+	}
+	
+	#[test]
+	fn swizzle_palette_swaps_blocks() {
+		let palette: Vec<u8> = (0..256 * 4).map(|byte| (byte / 4) as u8).collect();
+		let swizzled: Vec<u8> = swizzle_palette(&palette);
+		
+		// Entries 8-15 and 16-23 trade places in every block of 32, the rest stay
+		for block in 0..8 {
+			for entry in 0..32 {
+				let source: usize;
+				match entry {
+					8..=15 => source = entry + 8,
+					16..=23 => source = entry - 8,
+					_ => source = entry,
+				}
+				
+				assert_eq!(swizzled[4 * (32 * block + entry)], (32 * block + source) as u8);
+			}
+		}
+		
+		assert_eq!(swizzle_palette(&swizzled), palette);
+	}
+}
@@ -0,0 +1,33 @@
+use std::fs;
+use std::ops::Deref;
+
+use crate::PathBuf;
+
+// File in the temp directory for tests of readers that take a path, deleted when
+// it goes out of scope
+pub struct TempFile {
+	path: PathBuf,
+}
+
+impl TempFile {
+	pub fn new(name: &str, contents: &[u8]) -> TempFile {
+		let mut path: PathBuf = std::env::temp_dir();
+		path.push(format!("ghoul_test_{}_{}", std::process::id(), name));
+		fs::write(&path, contents).unwrap();
+		return TempFile { path: path };
+	}
+}
+
+impl Deref for TempFile {
+	type Target = PathBuf;
+	
+	fn deref(&self) -> &PathBuf {
+		return &self.path;
+	}
+}
+
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}